@@ -0,0 +1,44 @@
+use std::path::Path;
+
+use nix::errno::Errno;
+use nix::fcntl::AtFlags;
+use nix::unistd::AccessFlags;
+
+/// A kind of access to a file that can be checked for, as reported by `.readable`, `.writable`
+/// and `.executable`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+impl Access {
+    /// The number of kinds of access, for keeping a cached result for each.
+    pub const COUNT: usize = 3;
+}
+
+/// Checks whether the user running the program can access the file at `path`, using faccessat(2).
+/// By default, like access(2), this checks with the real user and group IDs; if `effective` is
+/// true, it uses the effective IDs instead, which is what actually opening the file would use.
+///
+/// Being denied access is not an error, but being unable to check, for example because the file no
+/// longer exists, is.
+pub fn check(path: &Path, access: Access, effective: bool) -> std::io::Result<bool> {
+    let mode = match access {
+        Access::Read => AccessFlags::R_OK,
+        Access::Write => AccessFlags::W_OK,
+        Access::Execute => AccessFlags::X_OK,
+    };
+    let flags = match effective {
+        true => AtFlags::AT_EACCESS,
+        false => AtFlags::empty(),
+    };
+
+    match nix::unistd::faccessat(None, path, mode, flags) {
+        Ok(()) => Ok(true),
+        // A file on a read-only filesystem can't be written to, even with permission:
+        Err(Errno::EACCES | Errno::EPERM | Errno::EROFS | Errno::ETXTBSY) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -1,71 +1,217 @@
+use std::borrow::Cow;
 use std::cell::OnceCell;
 
+use crate::access::Access;
 use crate::filter_non_fatal_errors;
+use crate::format::Format;
+use crate::hash::Algorithm;
+use crate::metadata::Metadata;
 use crate::program_state::ProgramState;
 use crate::treewalk::*;
 use crate::types::*;
 use crate::variables::*;
+use crate::xattr::Xattrs;
 
 pub mod analysis;
+pub mod builtins;
+pub mod functions;
+
+pub use builtins::*;
+pub use functions::*;
 
 pub struct FileState {
     pub path: std::path::PathBuf,
+    /// How many directories below the root of the walk the file is. The root itself is at depth
+    /// 0, so the last `depth` components of `path` are the path relative to the root.
+    pub depth: usize,
     /// A file's metadata is checked lazily, so that the extra stat() syscall can be avoided if
     /// the metadata is never queried.
-    md: OnceCell<std::io::Result<std::fs::Metadata>>,
+    md: OnceCell<std::io::Result<Metadata>>,
+    /// Whether to follow symlinks, so that the metadata is that of the file a symlink points to,
+    /// rather than of the link. The root's metadata is passed in already followed either way.
+    pub follow: bool,
+    /// The most bytes of the file's contents to read, if there is a limit.
+    pub max_bytes: Option<u64>,
+    /// Whether to check access with the effective IDs rather than the real ones.
+    effective_ids: bool,
+    /// Extended attributes are also read lazily, and all at once, since a program that looks at
+    /// one is likely to look at more.
+    xattrs: OnceCell<std::io::Result<Xattrs>>,
+    /// The hash of the file's contents with each algorithm, computed when first asked for.
+    hashes: [OnceCell<std::io::Result<String>>; Algorithm::COUNT],
+    lines: OnceCell<std::io::Result<u64>>,
+    /// The first few KB of the file, for telling what kind of file it is.
+    head: OnceCell<std::io::Result<Vec<u8>>>,
+    /// Whether the file can be read, written and executed, checked when first asked for.
+    access: [OnceCell<std::io::Result<bool>>; Access::COUNT],
 }
 
 impl FileState {
     /// Construct a new FileState. If the metadata is already available, pass Some(md) to set it,
-    /// otherwise, None means it will be queried from the filesystem later if needed.
-    pub fn new(path: std::path::PathBuf, md: Option<std::fs::Metadata>) -> Self {
-        let md_cell = OnceCell::new();
-        if let Some(md) = md {
-            md_cell.set(Ok(md)).unwrap()
+    /// otherwise, None means it will be queried from the filesystem later if needed. Whether to
+    /// follow symlinks and how much of the file's contents to read are taken from `args`.
+    pub fn new(
+        path: std::path::PathBuf,
+        depth: usize,
+        md: Option<Metadata>,
+        args: &crate::Args,
+    ) -> Self {
+        let md_cell = match md {
+            Some(md) => OnceCell::from(Ok(md)),
+            None => OnceCell::new(),
         };
 
-        FileState { path, md: md_cell }
+        FileState {
+            path,
+            depth,
+            md: md_cell,
+            follow: args.follow,
+            max_bytes: args.max_bytes,
+            effective_ids: args.effective_ids,
+            xattrs: OnceCell::new(),
+            hashes: Default::default(),
+            lines: OnceCell::new(),
+            head: OnceCell::new(),
+            access: Default::default(),
+        }
+    }
+
+    /// The path relative to the root of the walk, or "." for the root itself.
+    pub fn relpath(&self) -> std::path::PathBuf {
+        if self.depth == 0 {
+            return std::path::PathBuf::from(".");
+        }
+
+        let components: Vec<_> = self.path.components().collect();
+        components[components.len().saturating_sub(self.depth)..]
+            .iter()
+            .collect()
+    }
+
+    pub fn get_metadata(&self) -> &Result<Metadata, std::io::Error> {
+        self.md.get_or_init(|| match self.follow {
+            true => Metadata::follow(&self.path),
+            false => Metadata::lstatx(&self.path),
+        })
+    }
+
+    /// Get the hash of the file's contents.
+    pub fn get_hash(&self, algorithm: Algorithm) -> &Result<String, std::io::Error> {
+        self.hashes[algorithm as usize]
+            .get_or_init(|| crate::hash::hash_file(&self.path, algorithm))
+    }
+
+    /// Whether the file is a regular file, as opposed to a directory, device, etc. Only the
+    /// contents of regular files are looked at.
+    pub fn is_regular(&self) -> Result<bool, &std::io::Error> {
+        Ok(self.get_metadata().as_ref()?.is_file())
+    }
+
+    /// Get the number of lines in the file, or 0 if it isn't a regular file.
+    pub fn get_lines(&self) -> Result<u64, &std::io::Error> {
+        if !self.is_regular()? {
+            return Ok(0);
+        }
+
+        self.lines
+            .get_or_init(|| crate::content::count_lines(&self.path, self.max_bytes))
+            .as_ref()
+            .copied()
     }
 
-    pub fn get_metadata(&self) -> &Result<std::fs::Metadata, std::io::Error> {
-        self.md.get_or_init(|| std::fs::metadata(&self.path))
+    /// Get the first few KB of the file, or None if it isn't a regular file.
+    pub fn get_head(&self) -> Result<Option<&[u8]>, &std::io::Error> {
+        if !self.is_regular()? {
+            return Ok(None);
+        }
+
+        let head = self
+            .head
+            .get_or_init(|| crate::content::read_head(&self.path))
+            .as_ref()?;
+        Ok(Some(head))
+    }
+
+    /// Get whether the file looks like text, or false if it isn't a regular file.
+    pub fn get_is_text(&self) -> Result<bool, &std::io::Error> {
+        Ok(self.get_head()?.is_some_and(crate::content::is_text))
+    }
+
+    /// Get whether the user running the program can access the file in the way `access` says.
+    pub fn get_access(&self, access: Access) -> Result<bool, &std::io::Error> {
+        self.access[access as usize]
+            .get_or_init(|| crate::access::check(&self.path, access, self.effective_ids))
+            .as_ref()
+            .copied()
+    }
+
+    /// Get the file's extended attributes. Failing to read them is a runtime error.
+    pub fn get_xattrs(&self) -> Result<&Xattrs, crate::RuntimeError> {
+        self.xattrs
+            .get_or_init(|| crate::xattr::read_all(&self.path))
+            .as_ref()
+            .map_err(|e| {
+                crate::RuntimeError::new(&format!(
+                    "Could not read extended attributes of {}: {e}",
+                    self.path.display()
+                ))
+            })
     }
 }
 
 pub struct Program<'a, 'b, T: crate::SyncWrite> {
-    pub begin: Option<Action>,
-    pub end: Option<Action>,
+    /// BEGIN blocks, which run in source order before any files are processed.
+    pub begin: Vec<Action>,
+    /// END blocks, which run in source order after all files are processed.
+    pub end: Vec<Action>,
     pub routines: Vec<Routine>,
     pub prog_state: ProgramState<'a, 'b, T>,
 }
 
 impl<'a, T: crate::SyncWrite> Program<'a, '_, T> {
     pub fn run(&'a self, args: &crate::Args) -> Result<(), crate::RuntimeError> {
+        // BEGIN and END, and the routines for a root that isn't a directory, run on a thread of
+        // their own, so that they get as big a stack as the tree walk's threads:
+        std::thread::scope(|s| {
+            std::thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn_scoped(s, || self.run_on_this_thread(args))
+                .unwrap()
+                .join()
+                .unwrap()
+        })
+    }
+
+    fn run_on_this_thread(&'a self, args: &crate::Args) -> Result<(), crate::RuntimeError> {
         let path = &args.path;
 
-        let md = std::fs::metadata(path).unwrap();
+        // Like `find -H`, a symlink given as the root is followed even without `-L`, so that a
+        // link to a directory is walked:
+        let md = Metadata::follow(path).unwrap();
 
         self.begin_or_end(&self.begin)?;
 
         if md.is_dir() {
-            let f = FileState::new(path.into(), Some(md));
+            let f = FileState::new(path.into(), 0, Some(md), args);
             treewalk(args, &self.routines, f, &self.prog_state)?;
         } else {
-            let f = FileState::new(path.into(), Some(md));
+            let f = FileState::new(path.into(), 0, Some(md), args);
             run_routines(&self.routines, &f, &self.prog_state)?;
         }
 
         self.begin_or_end(&self.end)
     }
 
-    fn begin_or_end(&self, action: &Option<Action>) -> Result<(), crate::RuntimeError> {
-        filter_non_fatal_errors(if let Some(action) = action {
-            action
-                .interpret(None, &self.prog_state)
-                .inspect_err(|e| eprintln!("{e}"))
-        } else {
-            Ok(())
-        })
+    fn begin_or_end(&self, actions: &[Action]) -> Result<(), crate::RuntimeError> {
+        for action in actions.iter() {
+            filter_non_fatal_errors(
+                action
+                    .interpret(None, &self.prog_state)
+                    .inspect_err(|e| eprintln!("{e}")),
+            )?;
+        }
+
+        Ok(())
     }
 }
 
@@ -100,7 +246,11 @@ fn run_routines_inner<T: crate::SyncWrite>(
     for routine in routines.iter() {
         match &routine.cond {
             Some(cond) => {
-                if cond.expr.evaluate(Some(f), p.vars())?.is_truthy()? {
+                if cond
+                    .expr
+                    .evaluate_in(Some(f), &Frame::default(), p)?
+                    .is_truthy()?
+                {
                     routine.action.interpret(Some(f), p)?;
                 }
             }
@@ -120,43 +270,219 @@ pub struct Condition {
 pub enum Statement {
     Assignment(Assignment),
     Print(Vec<Expression>),
+    Printf(Printf),
+    If(IfStatement),
+    While(WhileStatement),
+    For(ForStatement),
+    ForIn(ForInStatement),
+    Break,
+    Continue,
+    Return(Option<Expression>),
+}
+
+/// What should happen after a statement has been interpreted. `Break` and `Continue` propagate up
+/// through enclosing statements until they reach the innermost loop, and `Return` propagates up
+/// to the function call.
+#[derive(Debug, PartialEq)]
+enum ControlFlow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
 }
 
 impl Statement {
+    /// Returns true if the statement ends with a brace-enclosed block, in which case it does not
+    /// need to be separated from the next statement by a ';'.
+    pub fn ends_with_block(&self) -> bool {
+        matches!(
+            self,
+            Statement::If(_) | Statement::While(_) | Statement::For(_) | Statement::ForIn(_)
+        )
+    }
+
     fn interpret<T: crate::SyncWrite>(
         &self,
         f: Option<&FileState>,
+        frame: &Frame,
         p: &ProgramState<T>,
-    ) -> crate::Result<()> {
+    ) -> crate::Result<ControlFlow> {
         match self {
-            Statement::Assignment(a) => {
-                p.vars().set_variable_expression(&a.lhs, f, &a.rhs)?;
-            }
+            Statement::Assignment(a) => a.interpret(f, frame, p)?,
             Statement::Print(exprs) => {
                 let mut exprs = exprs.iter();
                 let mut s = match exprs.nth(0) {
-                    Some(expr) => format!("{}", expr.evaluate(f, p.vars())?),
+                    Some(expr) => format!("{}", expr.evaluate_in(f, frame, p)?),
                     None => {
                         let _ = p.out.write("\n".as_bytes());
-                        return Ok(());
+                        return Ok(ControlFlow::Normal);
                     }
                 };
                 for expr in exprs {
-                    s.push_str(&format!(" {}", expr.evaluate(f, p.vars())?));
+                    s.push_str(&format!(" {}", expr.evaluate_in(f, frame, p)?));
                 }
                 s.push('\n');
                 let _ = p.out.write(s.as_bytes());
             }
+            Statement::Printf(pf) => {
+                let s = pf.bind(f, frame, p)?.evaluate(f, p.vars())?;
+                let _ = p.out.write(s.as_bytes());
+            }
+            Statement::If(i) => {
+                if i.cond.evaluate_in(f, frame, p)?.is_truthy()? {
+                    return interpret_statements(&i.then, f, frame, p);
+                } else if let Some(otherwise) = &i.otherwise {
+                    return interpret_statements(otherwise, f, frame, p);
+                }
+            }
+            Statement::While(w) => {
+                while w.cond.evaluate_in(f, frame, p)?.is_truthy()? {
+                    match interpret_statements(&w.body, f, frame, p)? {
+                        ControlFlow::Break => break,
+                        ControlFlow::Return(v) => return Ok(ControlFlow::Return(v)),
+                        _ => {}
+                    }
+                }
+            }
+            Statement::For(l) => {
+                if let Some(init) = &l.init {
+                    init.interpret(f, frame, p)?;
+                }
+                loop {
+                    if let Some(cond) = &l.cond {
+                        if !cond.evaluate_in(f, frame, p)?.is_truthy()? {
+                            break;
+                        }
+                    }
+                    match interpret_statements(&l.body, f, frame, p)? {
+                        ControlFlow::Break => break,
+                        ControlFlow::Return(v) => return Ok(ControlFlow::Return(v)),
+                        _ => {}
+                    }
+                    if let Some(step) = &l.step {
+                        step.interpret(f, frame, p)?;
+                    }
+                }
+            }
+            Statement::ForIn(l) => {
+                // The keys are copied out up front so that the array's lock isn't held while
+                // running the loop body, which may itself read or modify the array.
+                for key in p.vars().array_keys(l.array) {
+                    match l.key {
+                        Variable::Local(id) => frame.set(id, key),
+                        _ => p.vars().set_variable(&l.key, f, key)?,
+                    }
+                    match interpret_statements(&l.body, f, frame, p)? {
+                        ControlFlow::Break => break,
+                        ControlFlow::Return(v) => return Ok(ControlFlow::Return(v)),
+                        _ => {}
+                    }
+                }
+            }
+            Statement::Break => return Ok(ControlFlow::Break),
+            Statement::Continue => return Ok(ControlFlow::Continue),
+            Statement::Return(expr) => {
+                return Ok(ControlFlow::Return(match expr {
+                    Some(expr) => expr.evaluate_in(f, frame, p)?,
+                    None => Value::Int(0),
+                }))
+            }
         }
 
-        Ok(())
+        Ok(ControlFlow::Normal)
     }
 }
 
+/// Interprets a list of statements in order, stopping early if one of them is a `break`,
+/// `continue`, or `return`.
+fn interpret_statements<T: crate::SyncWrite>(
+    statements: &[Statement],
+    f: Option<&FileState>,
+    frame: &Frame,
+    p: &ProgramState<T>,
+) -> crate::Result<ControlFlow> {
+    for st in statements.iter() {
+        match st.interpret(f, frame, p)? {
+            ControlFlow::Normal => {}
+            flow => return Ok(flow),
+        }
+    }
+
+    Ok(ControlFlow::Normal)
+}
+
+#[derive(Debug)]
+pub struct IfStatement {
+    pub cond: Expression,
+    pub then: Vec<Statement>,
+    /// The `else` branch, if any. An `else if` is an `else` branch holding a single `If`.
+    pub otherwise: Option<Vec<Statement>>,
+}
+
+#[derive(Debug)]
+pub struct WhileStatement {
+    pub cond: Expression,
+    pub body: Vec<Statement>,
+}
+
+/// A C-style `for (init; cond; step)` loop.
+#[derive(Debug)]
+pub struct ForStatement {
+    pub init: Option<Assignment>,
+    /// A missing condition is always true.
+    pub cond: Option<Expression>,
+    pub step: Option<Assignment>,
+    pub body: Vec<Statement>,
+}
+
+/// A `for (key in arr)` loop, which runs its body once for each key in the array, in no
+/// particular order.
+#[derive(Debug)]
+pub struct ForInStatement {
+    pub key: Variable,
+    /// The id of the array being iterated over.
+    pub array: usize,
+    pub body: Vec<Statement>,
+}
+
 #[derive(Debug)]
 pub struct Assignment {
     pub lhs: Variable,
     pub rhs: Expression,
+    /// True for compound assignments like `+=`, in which case `rhs` is a binary operation whose
+    /// left side is `lhs`.
+    pub compound: bool,
+}
+
+impl Assignment {
+    fn interpret<T: crate::SyncWrite>(
+        &self,
+        f: Option<&FileState>,
+        frame: &Frame,
+        p: &ProgramState<T>,
+    ) -> crate::Result<()> {
+        if let Variable::Local(id) = self.lhs {
+            frame.set(id, self.rhs.evaluate_in(f, frame, p)?);
+            return Ok(());
+        }
+
+        let lhs = bind_variable(&self.lhs, f, frame, p)?;
+        let rhs = match &self.rhs {
+            // The left side of a compound assignment's operation is the assignee, which was
+            // just bound; reuse that rather than binding it again, which would call any functions
+            // in its subscript a second time.
+            Expression::Bin(b) if self.compound && matches!(lhs, Cow::Owned(_)) => {
+                Cow::Owned(Expression::Bin(BinaryOp {
+                    kind: b.kind,
+                    left: Box::new(Expression::Var(lhs.clone().into_owned())),
+                    right: Box::new(b.right.bind(f, frame, p)?.into_owned()),
+                }))
+            }
+            rhs => rhs.bind(f, frame, p)?,
+        };
+
+        p.vars().set_variable_expression(&lhs, f, &rhs)
+    }
 }
 
 #[derive(Debug)]
@@ -181,9 +507,7 @@ impl Action {
     ) -> crate::Result<()> {
         match &self.statements {
             Some(statements) => {
-                for st in statements.iter() {
-                    st.interpret(f, p)?
-                }
+                interpret_statements(statements, f, &Frame::default(), p)?;
             }
             // Default action is to print filename:
             None => {
@@ -207,15 +531,79 @@ pub struct BinaryOp {
 impl BinaryOp {
     fn evaluate(&self, f: Option<&FileState>, vars: &VariableState) -> crate::Result<Value> {
         let l = self.left.evaluate(f, vars)?;
+        // `and` and `or` only evaluate their right side if the left doesn't decide the result:
+        if matches!(self.kind, OpKind::And | OpKind::Or) {
+            let l = l.is_truthy()?;
+            if l == (self.kind == OpKind::Or) {
+                return Ok(Value::Boolean(l));
+            }
+            return Ok(Value::Boolean(self.right.evaluate(f, vars)?.is_truthy()?));
+        }
+
         let r = self.right.evaluate(f, vars)?;
 
         Ok(l.binary_op(r, self.kind)?)
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct UnaryOp {
+    pub kind: UnaryKind,
+    pub operand: Box<Expression>,
+}
+
+impl UnaryOp {
+    fn evaluate(&self, f: Option<&FileState>, vars: &VariableState) -> crate::Result<Value> {
+        Ok(self.operand.evaluate(f, vars)?.unary_op(self.kind)?)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UnaryKind {
+    /// Logical not, `!` or `not`
+    Not,
+    /// Numeric negation, `-`
+    Negate,
+}
+
+/// A printf-style formatting of `args`, used by both the `printf` statement and `sprintf()`.
+#[derive(Clone, Debug)]
+pub struct Printf {
+    pub format: FormatString,
+    pub args: Vec<Expression>,
+}
+
+#[derive(Clone, Debug)]
+pub enum FormatString {
+    /// A format that was a string literal, so it could be parsed, and checked against the number
+    /// of arguments, at compile time.
+    Compiled(Format),
+    /// A format that isn't known until runtime.
+    Dynamic(Box<Expression>),
+}
+
+impl Printf {
+    fn evaluate(&self, f: Option<&FileState>, vars: &VariableState) -> crate::Result<String> {
+        let mut args = Vec::with_capacity(self.args.len());
+        for arg in self.args.iter() {
+            args.push(arg.evaluate(f, vars)?);
+        }
+
+        Ok(match &self.format {
+            FormatString::Compiled(format) => format.format(&args)?,
+            FormatString::Dynamic(format) => {
+                let format = Format::parse(&format.evaluate(f, vars)?.to_string())
+                    .map_err(|e| crate::RuntimeError::new(&e))?;
+                format.format(&args)?
+            }
+        })
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum OpKind {
     EqualEqual,
+    NotEqual,
     Greater,
     GreaterEqual,
     Less,
@@ -224,26 +612,78 @@ pub enum OpKind {
     Minus,
     Multiply,
     Divide,
+    Modulo,
+    /// String concatenation, `..`
+    Concat,
+    /// Bitwise and, or, and exclusive or, which are mostly for testing bits of `.mode`
+    BitAnd,
+    BitOr,
+    BitXor,
+    Match,
+    NotMatch,
+    /// Shell glob match
+    Like,
+    /// Case-insensitive shell glob match
+    ILike,
     And,
     Or,
 }
 
+/// A regex or glob match whose pattern was a string literal, so the regex could be compiled once
+/// while compiling the program instead of once per file.
+#[derive(Clone, Debug)]
+pub struct RegexMatch {
+    /// True for `!~`, false otherwise.
+    pub negated: bool,
+    pub left: Box<Expression>,
+    pub regex: regex::Regex,
+}
+
+impl RegexMatch {
+    fn evaluate(&self, f: Option<&FileState>, vars: &VariableState) -> crate::Result<Value> {
+        let l = self.left.evaluate(f, vars)?;
+
+        Ok(Value::Boolean(
+            self.regex.is_match(&l.to_string()) != self.negated,
+        ))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Expression {
     Bin(BinaryOp),
+    Unary(UnaryOp),
+    Match(RegexMatch),
     Attr(Attribute),
     Atom(Value),
     Var(Variable),
+    Call(FunctionCall),
+    Builtin(BuiltinCall),
+    Sprintf(Printf),
 }
 
 impl Expression {
     /// Evaluate an expression within the context of the given `FileState` and `VariableState`.
+    ///
+    /// Any function calls or local variables in the expression must already have been bound; see
+    /// `Expression::bind()`.
     pub fn evaluate(&self, f: Option<&FileState>, vars: &VariableState) -> crate::Result<Value> {
         Ok(match self {
             Expression::Bin(op) => op.evaluate(f, vars)?,
+            Expression::Unary(op) => op.evaluate(f, vars)?,
+            Expression::Match(m) => m.evaluate(f, vars)?,
             Expression::Attr(attr) => attr.evaluate(f)?,
             Expression::Atom(v) => v.clone(),
             Expression::Var(var) => var.evaluate(f, vars)?,
+            Expression::Sprintf(pf) => Value::String(pf.evaluate(f, vars)?),
+            Expression::Builtin(b) => b.evaluate(f, vars)?,
+            Expression::Call(c) => {
+                return Err(crate::RuntimeError::new(&format!(
+                    "Function call to '{}' must be bound before evaluation",
+                    c.name
+                ))
+                .into())
+            }
         })
     }
 }
@@ -254,12 +694,53 @@ impl std::fmt::Display for Expression {
             Expression::Atom(val) => write!(f, "{:?}", val),
             Expression::Attr(attr) => write!(f, "{:?}", attr),
             Expression::Var(var) => write!(f, "{}", var),
+            Expression::Call(c) => {
+                write!(f, "{}(", c.name)?;
+                for arg in c.args.iter() {
+                    write!(f, "{} ", arg)?;
+                }
+                write!(f, ")")
+            }
+            Expression::Builtin(b) => {
+                write!(f, "{}(", b.builtin.name())?;
+                for arg in b.args.iter() {
+                    write!(f, "{} ", arg)?;
+                }
+                write!(f, ")")
+            }
+            Expression::Sprintf(pf) => {
+                match &pf.format {
+                    FormatString::Compiled(format) => write!(f, "sprintf({format} ")?,
+                    FormatString::Dynamic(format) => write!(f, "sprintf({format} ")?,
+                };
+                for arg in pf.args.iter() {
+                    write!(f, "{} ", arg)?;
+                }
+                write!(f, ")")
+            }
+            Expression::Unary(op) => write!(
+                f,
+                "({} {} )",
+                match op.kind {
+                    UnaryKind::Not => "!",
+                    UnaryKind::Negate => "-",
+                },
+                op.operand
+            ),
+            Expression::Match(m) => write!(
+                f,
+                "({} {} /{}/ )",
+                if m.negated { "!~" } else { "~" },
+                m.left,
+                m.regex
+            ),
             Expression::Bin(op) => {
                 write!(
                     f,
                     "({} ",
                     match op.kind {
                         OpKind::EqualEqual => "==",
+                        OpKind::NotEqual => "!=",
                         OpKind::Greater => ">",
                         OpKind::GreaterEqual => ">=",
                         OpKind::Less => "<",
@@ -268,6 +749,15 @@ impl std::fmt::Display for Expression {
                         OpKind::Minus => "-",
                         OpKind::Multiply => "*",
                         OpKind::Divide => "/",
+                        OpKind::Modulo => "%",
+                        OpKind::Concat => "..",
+                        OpKind::BitAnd => "&",
+                        OpKind::BitOr => "|",
+                        OpKind::BitXor => "^",
+                        OpKind::Match => "~",
+                        OpKind::NotMatch => "!~",
+                        OpKind::Like => "like",
+                        OpKind::ILike => "ilike",
                         OpKind::And => "and",
                         OpKind::Or => "or",
                     }
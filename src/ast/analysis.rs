@@ -1,26 +1,37 @@
 use std::collections::HashMap;
 
 use crate::ast::*;
+use crate::compiler::compile_error;
+use crate::scanner::Token;
 
 /// Analyzes the AST to replace `Variable::NotYetKnown` types with the appropriate kind of
-/// `Variable`.
+/// `Variable`, and to resolve function calls to the function they call.
 ///
 /// Returns the number of distinct scalar variables.
 pub fn analyze(
     known_arrays: &HashMap<String, usize>,
-    begin: &mut Option<Action>,
-    end: &mut Option<Action>,
+    begin: &mut [Action],
+    end: &mut [Action],
     routines: &mut [Routine],
+    functions: &mut [Function],
 ) -> crate::Result<usize> {
-    let mut vars = VarsMap::new(known_arrays);
+    let mut vars = VarsMap::new(known_arrays, functions);
 
-    analyze_action(begin.as_mut(), &mut vars)?;
+    for function in functions.iter_mut() {
+        analyze_function(function, &mut vars)?;
+    }
+
+    for action in begin.iter_mut() {
+        analyze_action(Some(action), &mut vars)?;
+    }
 
     for r in routines.iter_mut() {
         analyze_routine(r, &mut vars)?;
     }
 
-    analyze_action(end.as_mut(), &mut vars)?;
+    for action in end.iter_mut() {
+        analyze_action(Some(action), &mut vars)?;
+    }
 
     Ok(vars.num_scalars())
 }
@@ -31,35 +42,96 @@ fn analyze_expression(e: &mut Expression, vars: &mut VarsMap) -> crate::Result<(
     match e {
         Expression::Attr(_) => {}
         Expression::Atom(_) => {}
-        Expression::Var(v) => {
-            if let Variable::NotYetKnown(name) = v {
-                *e = Expression::Var(vars.new_variable(name));
-            }
-        }
+        Expression::Var(v) => analyze_variable(v, vars)?,
         Expression::Bin(b) => {
             analyze_expression(&mut b.left, vars)?;
             analyze_expression(&mut b.right, vars)?;
         }
+        Expression::Unary(op) => analyze_expression(&mut op.operand, vars)?,
+        Expression::Match(m) => analyze_expression(&mut m.left, vars)?,
+        Expression::Sprintf(pf) => analyze_printf(pf, vars)?,
+        Expression::Builtin(b) => {
+            for arg in b.args.iter_mut() {
+                analyze_expression(arg, vars)?;
+            }
+        }
+        Expression::Call(c) => {
+            let Some(&(id, num_params)) = vars.functions.get(&c.name) else {
+                return Err(compile_error(
+                    "Call to undefined function",
+                    &Token::Identifier(c.name.clone()),
+                ));
+            };
+            if c.args.len() > num_params {
+                return Err(compile_error(
+                    &format!("Too many arguments: function takes at most {num_params}"),
+                    &Token::Identifier(c.name.clone()),
+                ));
+            }
+            c.id = Some(id);
+
+            for arg in c.args.iter_mut() {
+                analyze_expression(arg, vars)?;
+            }
+        }
     };
 
     Ok(())
 }
 
-fn analyze_assignment(a: &mut Assignment, vars: &mut VarsMap) -> crate::Result<()> {
-    let new_lhs = match &a.lhs {
-        Variable::NotYetKnown(name) => vars.new_variable(name),
-        _ => a.lhs.clone(),
+fn analyze_printf(pf: &mut Printf, vars: &mut VarsMap) -> crate::Result<()> {
+    if let FormatString::Dynamic(format) = &mut pf.format {
+        analyze_expression(format, vars)?;
+    }
+
+    for arg in pf.args.iter_mut() {
+        analyze_expression(arg, vars)?;
+    }
+
+    Ok(())
+}
+
+/// Analyzes the body of a function, within which the function's parameters are local variables.
+fn analyze_function(function: &mut Function, vars: &mut VarsMap) -> crate::Result<()> {
+    for (i, param) in function.params.iter().enumerate() {
+        if vars.known_arrays.contains_key(param) {
+            return Err(compile_error(
+                "Function parameters cannot be used as arrays",
+                &Token::Identifier(param.clone()),
+            ));
+        }
+        vars.locals.insert(param.clone(), i);
+    }
+
+    let res = analyze_statements(&mut function.body, vars);
+    vars.locals.clear();
+    res
+}
+
+/// Resolves a `Variable::NotYetKnown` into the appropriate kind of variable, and analyzes the
+/// subscript expression of an array subscript.
+fn analyze_variable(v: &mut Variable, vars: &mut VarsMap) -> crate::Result<()> {
+    match v {
+        Variable::NotYetKnown(name) => *v = vars.new_variable(name),
+        Variable::ArrSub(arr) => analyze_expression(&mut arr.subscript, vars)?,
+        Variable::Scalar(_) => {}
+        Variable::Local(_) => {}
+        Variable::Arr(_) => {}
     };
 
-    match new_lhs {
+    Ok(())
+}
+
+fn analyze_assignment(a: &mut Assignment, vars: &mut VarsMap) -> crate::Result<()> {
+    analyze_variable(&mut a.lhs, vars)?;
+
+    match &a.lhs {
         // XXX: make this return an Error once I do error handling...
         Variable::Arr(_) => panic!("Cannot assign to an array name."),
         Variable::NotYetKnown(name) => panic!("Failed to resolve variable \"{name}\"."),
         _ => {}
     };
 
-    a.lhs = new_lhs;
-
     analyze_expression(&mut a.rhs, vars)?;
 
     Ok(())
@@ -74,6 +146,10 @@ fn analyze_action(mut action: Option<&mut Action>, vars: &mut VarsMap) -> crate:
         return Ok(());
     };
 
+    analyze_statements(statements, vars)
+}
+
+fn analyze_statements(statements: &mut [Statement], vars: &mut VarsMap) -> crate::Result<()> {
     for st in statements.iter_mut() {
         match st {
             Statement::Assignment(ref mut a) => analyze_assignment(a, vars)?,
@@ -82,6 +158,49 @@ fn analyze_action(mut action: Option<&mut Action>, vars: &mut VarsMap) -> crate:
                     analyze_expression(expr, vars)?;
                 }
             }
+            Statement::Printf(pf) => analyze_printf(pf, vars)?,
+            Statement::If(i) => {
+                analyze_expression(&mut i.cond, vars)?;
+                analyze_statements(&mut i.then, vars)?;
+                if let Some(otherwise) = &mut i.otherwise {
+                    analyze_statements(otherwise, vars)?;
+                }
+            }
+            Statement::While(w) => {
+                analyze_expression(&mut w.cond, vars)?;
+                analyze_statements(&mut w.body, vars)?;
+            }
+            Statement::For(l) => {
+                if let Some(init) = &mut l.init {
+                    analyze_assignment(init, vars)?;
+                }
+                if let Some(cond) = &mut l.cond {
+                    analyze_expression(cond, vars)?;
+                }
+                if let Some(step) = &mut l.step {
+                    analyze_assignment(step, vars)?;
+                }
+                analyze_statements(&mut l.body, vars)?;
+            }
+            Statement::ForIn(l) => {
+                if let Variable::NotYetKnown(name) = &l.key {
+                    if vars.known_arrays.contains_key(name) {
+                        return Err(compile_error(
+                            "Cannot use an array as a for loop key",
+                            &Token::Identifier(name.clone()),
+                        ));
+                    }
+                }
+                analyze_variable(&mut l.key, vars)?;
+                analyze_statements(&mut l.body, vars)?;
+            }
+            Statement::Break => {}
+            Statement::Continue => {}
+            Statement::Return(expr) => {
+                if let Some(expr) = expr {
+                    analyze_expression(expr, vars)?;
+                }
+            }
         };
     }
 
@@ -102,17 +221,34 @@ struct VarsMap<'a> {
 
     /// A mutable map for variables whose type will be discovered to be scalar.
     scalars_map: HashMap<String, usize>,
+
+    /// Maps the names of user-defined functions to their id and number of parameters.
+    functions: HashMap<String, (usize, usize)>,
+
+    /// The parameters of the function currently being analyzed, if any, which take precedence
+    /// over global variables of the same name.
+    locals: HashMap<String, usize>,
 }
 
 impl<'a> VarsMap<'a> {
-    fn new(known_arrays: &'a HashMap<String, usize>) -> Self {
+    fn new(known_arrays: &'a HashMap<String, usize>, functions: &[Function]) -> Self {
         VarsMap {
             known_arrays,
             scalars_map: HashMap::new(),
+            functions: functions
+                .iter()
+                .enumerate()
+                .map(|(id, f)| (f.name.clone(), (id, f.params.len())))
+                .collect(),
+            locals: HashMap::new(),
         }
     }
 
     fn new_variable(&mut self, name: &str) -> Variable {
+        if let Some(id) = self.locals.get(name) {
+            return Variable::Local(*id);
+        }
+
         match self.known_arrays.get(name) {
             Some(id) => Variable::Arr(*id),
             None => self.scalar(name),
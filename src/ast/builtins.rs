@@ -0,0 +1,448 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use crate::ast::*;
+use crate::hash::Algorithm;
+use crate::perm::PermSpec;
+use crate::RuntimeError;
+
+/// Functions that are built into the language. Calls to these are resolved while compiling, so
+/// that calling a function that is neither a builtin nor user-defined is a compile error.
+///
+/// String positions, as taken by `substr()` and returned by `index()`, count characters rather
+/// than bytes, and start at 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Builtin {
+    /// `length(s)`: the number of characters in `s`
+    Length,
+    /// `substr(s, start[, len])`: at most `len` characters of `s`, beginning at `start`
+    Substr,
+    /// `index(s, t)`: the position of the first occurrence of `t` in `s`, or 0 if there is none
+    Index,
+    ToLower,
+    ToUpper,
+    /// `split(s, arr[, sep])`: splits `s` on the string `sep`, or on runs of whitespace if `sep`
+    /// is omitted, into `arr[1]` through `arr[n]`, replacing the array's previous contents.
+    /// Returns n.
+    Split,
+    /// `sub(regex, replacement, s)`: `s` with the first match of `regex` replaced. As with the
+    /// regex crate, `$1` or `${name}` in the replacement refers to a capture group.
+    Sub,
+    /// `gsub(regex, replacement, s)`: like `sub()`, but replaces every match
+    Gsub,
+    /// `trim(s)`: `s` without leading and trailing whitespace
+    Trim,
+    /// `dirname(path)`: `path` without its last component, like dirname(1)
+    Dirname,
+    /// `basename(path)`: the last component of `path`, like basename(1)
+    Basename,
+    /// `join_path(path, ...)`: joins paths with '/'. A later absolute path replaces what came
+    /// before it, as with `Path::join`.
+    JoinPath,
+    /// `realpath(path)`: the canonical absolute path of `path`, resolving symlinks
+    Realpath,
+    /// `relpath_component(n)`: the `n`th directory or filename of the current file's path relative
+    /// to the root of the walk, counting from 1, or "" if there are fewer than `n`
+    RelpathComponent,
+    /// `strftime(fmt, t)`: formats the time `t` in the local timezone, like strftime(3). `%f` gives
+    /// the nanoseconds.
+    Strftime,
+    /// `date(s)`: parses a date such as "2024-01-31" or "2024-01-31 13:45" in the local timezone
+    /// into a time
+    Date,
+    /// `xattr(path, name)`: the value of the extended attribute `name` of `path`, or "" if it
+    /// doesn't have that attribute
+    Xattr,
+    /// `hash(path, algorithm)`: the hash of the contents of `path` as hex, where the algorithm is
+    /// one of "md5", "sha1", "sha256", "blake3" or "xxh3"
+    Hash,
+    /// `contains(path, regex)`: whether any line of `path` matches `regex`. False if `path` isn't
+    /// a regular file.
+    Contains,
+    /// `grep_count(path, regex)`: the number of lines of `path` that match `regex`, like `grep -c`
+    GrepCount,
+    /// `perm(mode, spec)`: whether `mode` passes a permission test such as "o+w" or "/022"; see
+    /// `PermSpec`
+    Perm,
+    /// `major(dev)` and `minor(dev)`: the major and minor parts of a device number, such as `.dev`
+    Major,
+    Minor,
+}
+
+impl Builtin {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "length" => Builtin::Length,
+            "substr" => Builtin::Substr,
+            "index" => Builtin::Index,
+            "tolower" => Builtin::ToLower,
+            "toupper" => Builtin::ToUpper,
+            "split" => Builtin::Split,
+            "sub" => Builtin::Sub,
+            "gsub" => Builtin::Gsub,
+            "trim" => Builtin::Trim,
+            "dirname" => Builtin::Dirname,
+            "basename" => Builtin::Basename,
+            "join_path" => Builtin::JoinPath,
+            "realpath" => Builtin::Realpath,
+            "relpath_component" => Builtin::RelpathComponent,
+            "strftime" => Builtin::Strftime,
+            "date" => Builtin::Date,
+            "xattr" => Builtin::Xattr,
+            "hash" => Builtin::Hash,
+            "contains" => Builtin::Contains,
+            "grep_count" => Builtin::GrepCount,
+            "perm" => Builtin::Perm,
+            "major" => Builtin::Major,
+            "minor" => Builtin::Minor,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Length => "length",
+            Builtin::Substr => "substr",
+            Builtin::Index => "index",
+            Builtin::ToLower => "tolower",
+            Builtin::ToUpper => "toupper",
+            Builtin::Split => "split",
+            Builtin::Sub => "sub",
+            Builtin::Gsub => "gsub",
+            Builtin::Trim => "trim",
+            Builtin::Dirname => "dirname",
+            Builtin::Basename => "basename",
+            Builtin::JoinPath => "join_path",
+            Builtin::Realpath => "realpath",
+            Builtin::RelpathComponent => "relpath_component",
+            Builtin::Strftime => "strftime",
+            Builtin::Date => "date",
+            Builtin::Xattr => "xattr",
+            Builtin::Hash => "hash",
+            Builtin::Contains => "contains",
+            Builtin::GrepCount => "grep_count",
+            Builtin::Perm => "perm",
+            Builtin::Major => "major",
+            Builtin::Minor => "minor",
+        }
+    }
+
+    /// The minimum and maximum number of arguments the builtin takes.
+    pub fn arity(&self) -> (usize, usize) {
+        match self {
+            Builtin::Length
+            | Builtin::ToLower
+            | Builtin::ToUpper
+            | Builtin::Trim
+            | Builtin::Dirname
+            | Builtin::Basename
+            | Builtin::Realpath
+            | Builtin::RelpathComponent
+            | Builtin::Date
+            | Builtin::Major
+            | Builtin::Minor => (1, 1),
+            Builtin::JoinPath => (2, usize::MAX),
+            Builtin::Index
+            | Builtin::Strftime
+            | Builtin::Xattr
+            | Builtin::Hash
+            | Builtin::Contains
+            | Builtin::GrepCount
+            | Builtin::Perm => (2, 2),
+            Builtin::Substr | Builtin::Split => (2, 3),
+            Builtin::Sub | Builtin::Gsub => (3, 3),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BuiltinCall {
+    pub builtin: Builtin,
+    pub args: Vec<Expression>,
+    /// For `sub()`, `gsub()`, `contains()` and `grep_count()` with a string literal pattern, the
+    /// pattern compiled ahead of time.
+    pub regex: Option<regex::Regex>,
+}
+
+impl BuiltinCall {
+    pub fn evaluate(&self, f: Option<&FileState>, vars: &VariableState) -> crate::Result<Value> {
+        let mut args = Vec::with_capacity(self.args.len());
+        for arg in self.args.iter() {
+            args.push(arg.evaluate(f, vars)?);
+        }
+
+        Ok(match self.builtin {
+            Builtin::Length => Value::Int(args[0].to_string().chars().count() as i64),
+            Builtin::Substr => {
+                let len = match args.get(2) {
+                    Some(len) => Some(len.clone().to_signed_int()?),
+                    None => None,
+                };
+                Value::String(substr(
+                    &args[0].to_string(),
+                    args[1].clone().to_signed_int()?,
+                    len,
+                ))
+            }
+            Builtin::Index => {
+                let s = args[0].to_string();
+                Value::Int(match s.find(&args[1].to_string()) {
+                    Some(i) => s[..i].chars().count() as i64 + 1,
+                    None => 0,
+                })
+            }
+            Builtin::ToLower => Value::String(args[0].to_string().to_lowercase()),
+            Builtin::ToUpper => Value::String(args[0].to_string().to_uppercase()),
+            Builtin::Trim => Value::String(args[0].to_string().trim().to_string()),
+            Builtin::Sub | Builtin::Gsub => {
+                let re = self.regex(&args[0])?;
+                let replacement = args[1].to_string();
+                let s = args[2].to_string();
+                Value::String(match self.builtin {
+                    Builtin::Sub => re.replace(&s, replacement.as_str()).into_owned(),
+                    _ => re.replace_all(&s, replacement.as_str()).into_owned(),
+                })
+            }
+            Builtin::Dirname => {
+                let path = args[0].to_string();
+                Value::String(match Path::new(&path).parent() {
+                    Some(p) if p.as_os_str().is_empty() => ".".to_string(),
+                    Some(p) => p.display().to_string(),
+                    None if path.starts_with('/') => "/".to_string(),
+                    None => ".".to_string(),
+                })
+            }
+            Builtin::Basename => {
+                let path = args[0].to_string();
+                Value::String(match Path::new(&path).file_name() {
+                    Some(name) => name.to_string_lossy().to_string(),
+                    None => path,
+                })
+            }
+            Builtin::JoinPath => {
+                let mut path = PathBuf::new();
+                for arg in args.iter() {
+                    path.push(arg.to_string());
+                }
+                Value::String(path.display().to_string())
+            }
+            Builtin::Realpath => {
+                let path = std::fs::canonicalize(args[0].to_string())
+                    .map_err(|e| crate::Error::from(&e))?;
+                Value::String(path.display().to_string())
+            }
+            Builtin::RelpathComponent => {
+                let Some(f) = f else {
+                    return Err(crate::Error::AttributeInBeginOrEnd);
+                };
+                let n = args[0].clone().to_signed_int()?;
+                let relpath = f.relpath();
+                Value::String(
+                    match n.checked_sub(1).and_then(|n| usize::try_from(n).ok()) {
+                        Some(n) if f.depth > 0 => match relpath.components().nth(n) {
+                            Some(c) => c.as_os_str().to_string_lossy().to_string(),
+                            None => String::new(),
+                        },
+                        _ => String::new(),
+                    },
+                )
+            }
+            Builtin::Strftime => {
+                // An integer is taken to be seconds since the epoch:
+                let t = match &args[1] {
+                    Value::Special(s) => s.time()?,
+                    t => t.clone().to_signed_int()?.saturating_mul(1_000_000_000),
+                };
+                Value::String(crate::time::strftime(&args[0].to_string(), t)?)
+            }
+            Builtin::Date => match crate::time::parse_date(&args[0].to_string()) {
+                Some(t) => SpecialValue::new_time(0, t),
+                None => {
+                    return Err(RuntimeError::new(&format!("Invalid date \"{}\"", args[0])).into())
+                }
+            },
+            Builtin::Xattr => Value::String(xattr(f, &args[0].to_string(), &args[1].to_string())?),
+            Builtin::Hash => Value::String(hash(f, &args[0].to_string(), &args[1].to_string())?),
+            Builtin::Contains | Builtin::GrepCount => {
+                let re = self.regex(&args[1])?;
+                let first_only = self.builtin == Builtin::Contains;
+                let count = grep(f, &args[0].to_string(), &re, first_only)?;
+                match self.builtin {
+                    Builtin::Contains => Value::Boolean(count > 0),
+                    _ => Value::Int(count.try_into().unwrap()),
+                }
+            }
+            Builtin::Perm => {
+                let mode = args[0].clone().to_mode()?;
+                let Some(spec) = PermSpec::parse(&args[1].to_string()) else {
+                    return Err(RuntimeError::new(&format!(
+                        "Invalid permission test \"{}\"",
+                        args[1]
+                    ))
+                    .into());
+                };
+                Value::Boolean(spec.matches(mode))
+            }
+            Builtin::Major | Builtin::Minor => {
+                // An integer is taken to be a raw device number:
+                let dev = match &args[0] {
+                    Value::Special(s) => s.devno()?,
+                    dev => {
+                        let dev = dev.clone().to_signed_int()?;
+                        dev.try_into().map_err(|_| {
+                            RuntimeError::new(&format!("Invalid device number {dev}"))
+                        })?
+                    }
+                };
+                Value::Int(
+                    match self.builtin {
+                        Builtin::Major => crate::metadata::major(dev),
+                        _ => crate::metadata::minor(dev),
+                    }
+                    .into(),
+                )
+            }
+            Builtin::Split => {
+                return Err(
+                    RuntimeError::new("Call to split() must be bound before evaluation").into(),
+                )
+            }
+        })
+    }
+
+    /// The regex given by `pattern`, or the one compiled ahead of time if it was a literal.
+    fn regex(&self, pattern: &Value) -> Result<Cow<'_, regex::Regex>, RuntimeError> {
+        match &self.regex {
+            Some(re) => Ok(Cow::Borrowed(re)),
+            None => match regex::Regex::new(&pattern.to_string()) {
+                Ok(re) => Ok(Cow::Owned(re)),
+                Err(e) => Err(RuntimeError::new(&format!(
+                    "Invalid regular expression: {e}"
+                ))),
+            },
+        }
+    }
+
+    /// Splits the first argument into the array named by the second. This modifies a global
+    /// variable, so unlike the other builtins it happens while binding; see `Expression::bind()`.
+    pub fn split<T: crate::SyncWrite>(
+        &self,
+        f: Option<&FileState>,
+        frame: &Frame,
+        p: &ProgramState<T>,
+    ) -> crate::Result<Value> {
+        let Expression::Var(Variable::Arr(id)) = self.args[1] else {
+            panic!("The second argument to split() must be an array.");
+        };
+
+        let s = self.args[0].evaluate_in(f, frame, p)?.to_string();
+        let pieces: Vec<&str> = match self.args.get(2) {
+            Some(sep) => {
+                let sep = sep.evaluate_in(f, frame, p)?.to_string();
+                if s.is_empty() {
+                    Vec::new()
+                } else if sep.is_empty() {
+                    s.matches(|_| true).collect()
+                } else {
+                    s.split(sep.as_str()).collect()
+                }
+            }
+            None => s.split_whitespace().collect(),
+        };
+
+        let n = pieces.len() as i64;
+        p.vars().set_array(
+            id,
+            pieces
+                .into_iter()
+                .enumerate()
+                .map(|(i, piece)| (Value::Int(i as i64 + 1), Value::String(piece.to_string())))
+                .collect(),
+        );
+        Ok(Value::Int(n))
+    }
+}
+
+/// Up to `len` characters of `s` starting at the 1-based position `start`. Positions outside of
+/// the string are ignored, so `substr("hello", 0, 2)` is `"h"`.
+fn substr(s: &str, start: i64, len: Option<i64>) -> String {
+    let end = match len {
+        Some(len) => start.saturating_add(len.max(0)),
+        None => i64::MAX,
+    };
+    let start = start.max(1);
+    if end <= start {
+        return String::new();
+    }
+
+    s.chars()
+        .skip((start - 1) as usize)
+        .take((end - start).try_into().unwrap_or(usize::MAX))
+        .collect()
+}
+
+/// Looks up an extended attribute. The current file's attributes are cached, so use those if
+/// `path` is the current file.
+fn xattr(f: Option<&FileState>, path: &str, name: &str) -> Result<String, RuntimeError> {
+    let value = match f {
+        Some(f) if f.path.as_os_str() == path => f
+            .get_xattrs()?
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.clone()),
+        _ => crate::xattr::get(Path::new(path), name).map_err(|e| {
+            RuntimeError::new(&format!(
+                "Could not read extended attribute {name} of {path}: {e}"
+            ))
+        })?,
+    };
+
+    Ok(match value {
+        Some(value) => String::from_utf8_lossy(&value).to_string(),
+        None => String::new(),
+    })
+}
+
+/// Hashes the contents of a file. The current file's hashes are cached, so use those if `path` is
+/// the current file. Failing to read the file is not a fatal error.
+fn hash(f: Option<&FileState>, path: &str, algorithm: &str) -> crate::Result<String> {
+    let Some(algorithm) = Algorithm::from_name(algorithm) else {
+        return Err(RuntimeError::new(&format!("Unknown hash algorithm \"{algorithm}\"")).into());
+    };
+
+    match f {
+        Some(f) if f.path.as_os_str() == path => Ok(f.get_hash(algorithm).as_ref()?.clone()),
+        _ => crate::hash::hash_file(Path::new(path), algorithm).map_err(|e| (&e).into()),
+    }
+}
+
+/// Counts the lines of a file that match `re`, or 0 if it isn't a regular file. If `path` is the
+/// current file, its metadata is already known. Like the current file, a symlink to a regular file
+/// only counts as one when following symlinks. Failing to read the file is not a fatal error.
+fn grep(
+    f: Option<&FileState>,
+    path: &str,
+    re: &regex::Regex,
+    first_only: bool,
+) -> crate::Result<u64> {
+    // The options are kept with each file, so in BEGIN and END, symlinks aren't followed and
+    // there is no limit:
+    let follow = f.is_some_and(|f| f.follow);
+    let max_bytes = f.and_then(|f| f.max_bytes);
+
+    let is_regular = match f {
+        Some(f) if f.path.as_os_str() == path => f.is_regular()?,
+        _ => match follow {
+            true => std::fs::metadata(path),
+            false => std::fs::symlink_metadata(path),
+        }
+        .map_err(|e| crate::Error::from(&e))?
+        .is_file(),
+    };
+
+    if !is_regular {
+        return Ok(0);
+    }
+
+    crate::content::grep_count(Path::new(path), re, max_bytes, first_only).map_err(|e| (&e).into())
+}
@@ -0,0 +1,279 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use crate::ast::*;
+
+/// Calls nested deeper than this are assumed to be runaway recursion, and are a runtime error
+/// rather than a stack overflow.
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+/// The stack size for the threads that run a program. A call goes through a few dozen frames in
+/// the interpreter, so the usual 2 MiB for a spawned thread isn't enough for `MAX_CALL_DEPTH`
+/// calls, especially in a debug build. Only the stack that is used is actually allocated.
+pub const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// A user-defined function.
+///
+/// Parameters are local to a call, while any other variable used in the body refers to a global.
+/// As in awk, a function can be called with fewer arguments than it has parameters, in which case
+/// the remaining parameters start out as 0, and so can be used as local variables.
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FunctionCall {
+    pub name: String,
+    /// Index into the program's functions. This is None until the call is resolved during
+    /// analysis, since functions can be called before they are defined.
+    pub id: Option<usize>,
+    pub args: Vec<Expression>,
+}
+
+impl FunctionCall {
+    fn call<T: crate::SyncWrite>(
+        &self,
+        f: Option<&FileState>,
+        frame: &Frame,
+        p: &ProgramState<T>,
+    ) -> crate::Result<Value> {
+        let Some(id) = self.id else {
+            return Err(crate::RuntimeError::new(&format!(
+                "Attempted to call unresolved function '{}'",
+                self.name
+            ))
+            .into());
+        };
+        let function = p.function(id);
+
+        if frame.depth >= MAX_CALL_DEPTH {
+            return Err(crate::RuntimeError::new(&format!(
+                "Maximum call depth of {MAX_CALL_DEPTH} exceeded while calling '{}'",
+                function.name
+            ))
+            .into());
+        }
+
+        let mut locals = Vec::with_capacity(function.params.len());
+        for arg in self.args.iter() {
+            locals.push(arg.evaluate_in(f, frame, p)?);
+        }
+        locals.resize(function.params.len(), Value::Int(0));
+
+        let callee = Frame {
+            locals: RefCell::new(locals),
+            depth: frame.depth + 1,
+        };
+
+        Ok(match interpret_statements(&function.body, f, &callee, p)? {
+            ControlFlow::Return(v) => v,
+            _ => Value::Int(0),
+        })
+    }
+}
+
+/// The local variables of a single function call. Code outside of any function runs with an empty
+/// frame.
+#[derive(Default)]
+pub struct Frame {
+    locals: RefCell<Vec<Value>>,
+    /// How many calls deep this frame is.
+    depth: usize,
+}
+
+impl Frame {
+    fn get(&self, id: usize) -> Value {
+        self.locals.borrow()[id].clone()
+    }
+
+    pub fn set(&self, id: usize, v: Value) {
+        self.locals.borrow_mut()[id] = v;
+    }
+}
+
+impl Expression {
+    /// Evaluate an expression that may contain function calls or local variables.
+    pub fn evaluate_in<T: crate::SyncWrite>(
+        &self,
+        f: Option<&FileState>,
+        frame: &Frame,
+        p: &ProgramState<T>,
+    ) -> crate::Result<Value> {
+        self.bind(f, frame, p)?.evaluate(f, p.vars())
+    }
+
+    /// Replace the function calls and local variables in an expression with their values.
+    ///
+    /// This has to happen before the expression is evaluated against the global variables, since
+    /// evaluating a function call can run arbitrary statements, which must not happen while the
+    /// global variables are locked for an assignment. Binding first means that assignments such
+    /// as `arr[f(.size)] += 1` still happen atomically.
+    ///
+    /// Expressions without any calls or locals, which are the common case, are not copied.
+    pub fn bind<T: crate::SyncWrite>(
+        &self,
+        f: Option<&FileState>,
+        frame: &Frame,
+        p: &ProgramState<T>,
+    ) -> crate::Result<Cow<'_, Expression>> {
+        Ok(match self {
+            Expression::Attr(_) => Cow::Borrowed(self),
+            Expression::Atom(_) => Cow::Borrowed(self),
+            Expression::Var(v) => match bind_variable(v, f, frame, p)? {
+                Cow::Borrowed(_) => Cow::Borrowed(self),
+                Cow::Owned(Variable::Local(id)) => Cow::Owned(Expression::Atom(frame.get(id))),
+                Cow::Owned(v) => Cow::Owned(Expression::Var(v)),
+            },
+            // The right side of `and` and `or` is only evaluated if it's needed, so if binding it
+            // would run anything, the left side has to be evaluated first to find out:
+            Expression::Bin(b)
+                if matches!(b.kind, OpKind::And | OpKind::Or) && b.right.has_side_effects() =>
+            {
+                let left = b.left.evaluate_in(f, frame, p)?.is_truthy()?;
+                let value = match (b.kind, left) {
+                    (OpKind::And, false) => false,
+                    (OpKind::Or, true) => true,
+                    _ => b.right.evaluate_in(f, frame, p)?.is_truthy()?,
+                };
+                Cow::Owned(Expression::Atom(Value::Boolean(value)))
+            }
+            Expression::Bin(b) => {
+                let left = b.left.bind(f, frame, p)?;
+                let right = b.right.bind(f, frame, p)?;
+                match (left, right) {
+                    (Cow::Borrowed(_), Cow::Borrowed(_)) => Cow::Borrowed(self),
+                    (left, right) => Cow::Owned(Expression::Bin(BinaryOp {
+                        kind: b.kind,
+                        left: Box::new(left.into_owned()),
+                        right: Box::new(right.into_owned()),
+                    })),
+                }
+            }
+            Expression::Unary(op) => match op.operand.bind(f, frame, p)? {
+                Cow::Borrowed(_) => Cow::Borrowed(self),
+                Cow::Owned(operand) => Cow::Owned(Expression::Unary(UnaryOp {
+                    kind: op.kind,
+                    operand: Box::new(operand),
+                })),
+            },
+            Expression::Match(m) => match m.left.bind(f, frame, p)? {
+                Cow::Borrowed(_) => Cow::Borrowed(self),
+                Cow::Owned(left) => Cow::Owned(Expression::Match(RegexMatch {
+                    negated: m.negated,
+                    left: Box::new(left),
+                    regex: m.regex.clone(),
+                })),
+            },
+            Expression::Call(c) => Cow::Owned(Expression::Atom(c.call(f, frame, p)?)),
+            Expression::Builtin(b) if b.builtin == Builtin::Split => {
+                Cow::Owned(Expression::Atom(b.split(f, frame, p)?))
+            }
+            Expression::Builtin(b) => {
+                let mut args = Vec::with_capacity(b.args.len());
+                let mut changed = false;
+                for arg in b.args.iter() {
+                    let arg = arg.bind(f, frame, p)?;
+                    changed |= matches!(arg, Cow::Owned(_));
+                    args.push(arg);
+                }
+
+                if changed {
+                    Cow::Owned(Expression::Builtin(BuiltinCall {
+                        builtin: b.builtin,
+                        args: args.into_iter().map(|arg| arg.into_owned()).collect(),
+                        regex: b.regex.clone(),
+                    }))
+                } else {
+                    Cow::Borrowed(self)
+                }
+            }
+            Expression::Sprintf(pf) => match pf.bind(f, frame, p)? {
+                Cow::Borrowed(_) => Cow::Borrowed(self),
+                Cow::Owned(pf) => Cow::Owned(Expression::Sprintf(pf)),
+            },
+        })
+    }
+}
+
+impl Expression {
+    /// Whether binding the expression runs anything, because it calls a function or `split()`.
+    fn has_side_effects(&self) -> bool {
+        match self {
+            Expression::Attr(_) | Expression::Atom(_) => false,
+            Expression::Var(Variable::ArrSub(arr)) => arr.subscript.has_side_effects(),
+            Expression::Var(_) => false,
+            Expression::Bin(b) => b.left.has_side_effects() || b.right.has_side_effects(),
+            Expression::Unary(op) => op.operand.has_side_effects(),
+            Expression::Match(m) => m.left.has_side_effects(),
+            Expression::Call(_) => true,
+            Expression::Builtin(b) => {
+                b.builtin == Builtin::Split || b.args.iter().any(|arg| arg.has_side_effects())
+            }
+            Expression::Sprintf(pf) => {
+                matches!(&pf.format, FormatString::Dynamic(format) if format.has_side_effects())
+                    || pf.args.iter().any(|arg| arg.has_side_effects())
+            }
+        }
+    }
+}
+
+impl Printf {
+    /// Bind the local variables and function calls in the format and arguments; see
+    /// `Expression::bind()`.
+    pub fn bind<T: crate::SyncWrite>(
+        &self,
+        f: Option<&FileState>,
+        frame: &Frame,
+        p: &ProgramState<T>,
+    ) -> crate::Result<Cow<'_, Printf>> {
+        let format = match &self.format {
+            FormatString::Dynamic(format) => match format.bind(f, frame, p)? {
+                Cow::Borrowed(_) => None,
+                Cow::Owned(format) => Some(FormatString::Dynamic(Box::new(format))),
+            },
+            FormatString::Compiled(_) => None,
+        };
+
+        let mut args = Vec::with_capacity(self.args.len());
+        let mut changed = format.is_some();
+        for arg in self.args.iter() {
+            let arg = arg.bind(f, frame, p)?;
+            changed |= matches!(arg, Cow::Owned(_));
+            args.push(arg);
+        }
+
+        if !changed {
+            return Ok(Cow::Borrowed(self));
+        }
+
+        Ok(Cow::Owned(Printf {
+            format: format.unwrap_or_else(|| self.format.clone()),
+            args: args.into_iter().map(|arg| arg.into_owned()).collect(),
+        }))
+    }
+}
+
+/// Bind the local variables and function calls in a variable; see `Expression::bind()`.
+///
+/// A local variable is returned as-is, but owned, so that the caller can tell it needs replacing.
+pub fn bind_variable<'v, T: crate::SyncWrite>(
+    v: &'v Variable,
+    f: Option<&FileState>,
+    frame: &Frame,
+    p: &ProgramState<T>,
+) -> crate::Result<Cow<'v, Variable>> {
+    Ok(match v {
+        Variable::Local(_) => Cow::Owned(v.clone()),
+        Variable::ArrSub(arr) => match arr.subscript.bind(f, frame, p)? {
+            Cow::Borrowed(_) => Cow::Borrowed(v),
+            Cow::Owned(subscript) => Cow::Owned(Variable::ArrSub(ArraySubscript {
+                id: arr.id,
+                subscript: Box::new(subscript),
+            })),
+        },
+        _ => Cow::Borrowed(v),
+    })
+}
@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 
 use crate::ast::*;
+use crate::format::Format;
 use crate::program_state::ProgramState;
 use crate::scanner::*;
+use crate::types::*;
 use crate::variables::*;
 
 pub fn compile_error(msg: &str, t: &Token) -> crate::Error {
@@ -19,6 +21,16 @@ pub struct Compiler<'a> {
     /// immediately which type they refer to.
     known_arrays: HashMap<String, usize>,
     num_arrays: usize,
+    /// How many loops the statement currently being compiled is nested in, so that `break` and
+    /// `continue` outside of any loop can be rejected.
+    loop_depth: usize,
+    /// Whether the statement currently being compiled is inside of a function body, where
+    /// `return` is allowed.
+    in_function: bool,
+    functions: Vec<Function>,
+    /// The value of `now`, which is fixed when the program starts so that it is the same for
+    /// every file.
+    now: Value,
 }
 
 impl<'a> Compiler<'a> {
@@ -29,6 +41,10 @@ impl<'a> Compiler<'a> {
             next: Token::Error("uninitialized".to_string()),
             known_arrays: HashMap::new(),
             num_arrays: 0,
+            loop_depth: 0,
+            in_function: false,
+            functions: Vec::new(),
+            now: SpecialValue::new_time(0, crate::time::now()),
         }
     }
 
@@ -38,9 +54,8 @@ impl<'a> Compiler<'a> {
     ) -> crate::Result<Program<'b, 'c, T>> {
         self.next();
 
-        // XXX: allow multiple begin and end routines
-        let mut begin = None;
-        let mut end = None;
+        let mut begin = Vec::new();
+        let mut end = Vec::new();
         let mut routines = Vec::new();
         loop {
             match self.peek() {
@@ -48,12 +63,17 @@ impl<'a> Compiler<'a> {
                 Token::Begin => {
                     self.next();
                     self.eat(Token::LeftBrace, "Expected '{' after 'BEGIN'")?;
-                    begin = Some(self.action()?);
+                    begin.push(self.action()?);
                 }
                 Token::End => {
                     self.next();
                     self.eat(Token::LeftBrace, "Expected '{' after 'END'")?;
-                    end = Some(self.action()?);
+                    end.push(self.action()?);
+                }
+                Token::Function => {
+                    self.next();
+                    let function = self.function()?;
+                    self.functions.push(function);
                 }
                 _ => routines.push(self.routine()?),
             };
@@ -67,14 +87,21 @@ impl<'a> Compiler<'a> {
             _ => routines,
         };
 
-        let num_scalars =
-            analysis::analyze(&self.known_arrays, &mut begin, &mut end, &mut routines)?;
+        let mut functions = std::mem::take(&mut self.functions);
+
+        let num_scalars = analysis::analyze(
+            &self.known_arrays,
+            &mut begin,
+            &mut end,
+            &mut routines,
+            &mut functions,
+        )?;
 
         Ok(Program {
             begin,
             end,
             routines,
-            prog_state: ProgramState::new(num_scalars, self.num_arrays, out),
+            prog_state: ProgramState::new(num_scalars, self.num_arrays, functions, out),
         })
     }
 
@@ -96,6 +123,73 @@ impl<'a> Compiler<'a> {
         &self.current
     }
 
+    /// Parses a function definition, after the `function` keyword.
+    fn function(&mut self) -> crate::Result<Function> {
+        let name = match self.next() {
+            Token::Identifier(name) => name.clone(),
+            tok => {
+                return Err(compile_error(
+                    "Expected a function name after 'function'",
+                    tok,
+                ))
+            }
+        };
+        if name == "sprintf" || Builtin::from_name(&name).is_some() {
+            return Err(compile_error(
+                "Cannot redefine a builtin function",
+                &self.current,
+            ));
+        }
+        if self.functions.iter().any(|f| f.name == name) {
+            return Err(compile_error(
+                "A function with this name is already defined",
+                &self.current,
+            ));
+        }
+
+        self.eat(Token::LeftParen, "Expected '(' after function name")?;
+        let mut params: Vec<String> = Vec::new();
+        if *self.peek() == Token::RightParen {
+            self.next();
+        } else {
+            loop {
+                match self.next() {
+                    Token::Identifier(param) if params.contains(param) => {
+                        return Err(compile_error("Duplicate parameter name", &self.current))
+                    }
+                    Token::Identifier(param) if param == "now" => {
+                        return Err(compile_error(
+                            "Cannot use 'now' as a parameter name",
+                            &self.current,
+                        ))
+                    }
+                    Token::Identifier(param) => params.push(param.clone()),
+                    tok => return Err(compile_error("Expected a parameter name", tok)),
+                };
+                match self.next() {
+                    Token::Comma => continue,
+                    Token::RightParen => break,
+                    tok => {
+                        return Err(compile_error(
+                            "Expected ',' or ')' after a parameter name",
+                            tok,
+                        ))
+                    }
+                };
+            }
+        }
+
+        self.in_function = true;
+        let body = self.block("Expected '{' after function parameters");
+        self.in_function = false;
+
+        Ok(Function {
+            name,
+            params,
+            body: body?,
+        })
+    }
+
     fn routine(&mut self) -> crate::Result<Routine> {
         let cond = match self.peek() {
             Token::LeftBrace => None,
@@ -138,13 +232,17 @@ impl<'a> Compiler<'a> {
     fn statements(&mut self) -> crate::Result<Vec<Statement>> {
         let mut statements = Vec::new();
         loop {
+            // Statements that end in a block, like `if`, don't need a ';' after them:
+            let mut ends_with_block = false;
             if let Some(st) = self.statement()? {
+                ends_with_block = st.ends_with_block();
                 statements.push(st);
             };
             match self.peek() {
                 Token::RightBrace => break,
                 // XXX: allow newline to separate statement?
                 Token::Semicolon => self.next(),
+                _ if ends_with_block => continue,
                 tok => {
                     return Err(compile_error(
                         "Expected either ';' or '}}' after a statement",
@@ -159,29 +257,55 @@ impl<'a> Compiler<'a> {
 
     fn statement(&mut self) -> crate::Result<Option<Statement>> {
         let statement = match self.peek() {
-            Token::Identifier(name) => {
-                let name = name.clone();
-                self.next();
-                let lhs = self.variable(name)?;
-                let rhs = match self.next() {
-                    Token::Equal => self.expression(0)?,
-                    Token::PlusEqual => self.compound_assignment(lhs.clone(), Token::PlusEqual)?,
-                    Token::MinusEqual => {
-                        self.compound_assignment(lhs.clone(), Token::MinusEqual)?
-                    }
-                    tok => {
-                        return Err(compile_error(
-                            "Expected an assignment after identifier",
-                            tok,
-                        ))
-                    }
-                };
-                Some(Statement::Assignment(Assignment { lhs, rhs }))
-            }
+            Token::Identifier(_) => Some(Statement::Assignment(self.assignment()?)),
             Token::Print => {
                 self.next();
                 Some(Statement::Print(self.expressions()?))
             }
+            Token::Printf => {
+                self.next();
+                let args = self.expressions()?;
+                Some(Statement::Printf(self.printf(args)?))
+            }
+            Token::If => {
+                self.next();
+                Some(Statement::If(self.if_statement()?))
+            }
+            Token::While => {
+                self.next();
+                Some(Statement::While(self.while_statement()?))
+            }
+            Token::For => {
+                self.next();
+                Some(self.for_statement()?)
+            }
+            Token::Break | Token::Continue if self.loop_depth == 0 => {
+                return Err(compile_error(
+                    "'break' and 'continue' are only allowed inside a loop",
+                    self.peek(),
+                ))
+            }
+            Token::Return if !self.in_function => {
+                return Err(compile_error(
+                    "'return' is only allowed inside a function",
+                    self.peek(),
+                ))
+            }
+            Token::Return => {
+                self.next();
+                Some(Statement::Return(match self.peek() {
+                    Token::Semicolon | Token::RightBrace => None,
+                    _ => Some(self.expression(0)?),
+                }))
+            }
+            Token::Break => {
+                self.next();
+                Some(Statement::Break)
+            }
+            Token::Continue => {
+                self.next();
+                Some(Statement::Continue)
+            }
             Token::RightBrace => None,
             Token::Semicolon => None,
             tok => return Err(compile_error("Expected beginning of statement", tok)),
@@ -190,6 +314,172 @@ impl<'a> Compiler<'a> {
         Ok(statement)
     }
 
+    fn assignment(&mut self) -> crate::Result<Assignment> {
+        let name = match self.next() {
+            Token::Identifier(name) => name.clone(),
+            tok => return Err(compile_error("Expected an identifier", tok)),
+        };
+        self.assignment_to(name)
+    }
+
+    /// Parses the rest of an assignment, after the name of the variable being assigned to.
+    fn assignment_to(&mut self, name: String) -> crate::Result<Assignment> {
+        if name == "now" {
+            return Err(compile_error("Cannot assign to 'now'", &self.current));
+        }
+        let lhs = self.variable(name)?;
+        let (rhs, compound) = match self.next() {
+            Token::Equal => (self.expression(0)?, false),
+            Token::PlusEqual => (
+                self.compound_assignment(lhs.clone(), Token::PlusEqual)?,
+                true,
+            ),
+            Token::MinusEqual => (
+                self.compound_assignment(lhs.clone(), Token::MinusEqual)?,
+                true,
+            ),
+            tok => {
+                return Err(compile_error(
+                    "Expected an assignment after identifier",
+                    tok,
+                ))
+            }
+        };
+
+        Ok(Assignment { lhs, rhs, compound })
+    }
+
+    /// Parses the rest of an `if` statement, after the `if` keyword. An `else if` is represented as
+    /// an `else` block containing a single nested `if` statement.
+    fn if_statement(&mut self) -> crate::Result<IfStatement> {
+        self.eat(Token::LeftParen, "Expected '(' after 'if'")?;
+        let cond = self.expression(0)?;
+        self.eat(Token::RightParen, "Expected ')' after if condition")?;
+
+        let then = self.block("Expected '{' after if condition")?;
+
+        let otherwise = match self.peek() {
+            Token::Else => {
+                self.next();
+                match self.peek() {
+                    Token::If => {
+                        self.next();
+                        Some(vec![Statement::If(self.if_statement()?)])
+                    }
+                    _ => Some(self.block("Expected '{' or 'if' after 'else'")?),
+                }
+            }
+            _ => None,
+        };
+
+        Ok(IfStatement {
+            cond,
+            then,
+            otherwise,
+        })
+    }
+
+    /// Parses the rest of a `while` statement, after the `while` keyword.
+    fn while_statement(&mut self) -> crate::Result<WhileStatement> {
+        self.eat(Token::LeftParen, "Expected '(' after 'while'")?;
+        let cond = self.expression(0)?;
+        self.eat(Token::RightParen, "Expected ')' after while condition")?;
+
+        let body = self.loop_body("Expected '{' after while condition")?;
+
+        Ok(WhileStatement { cond, body })
+    }
+
+    /// Parses the rest of a `for` statement, after the `for` keyword. This is either a loop over
+    /// the keys of an array, `for (key in arr)`, or a C-style loop. Each of the C-style loop's
+    /// initializer, condition, and step may be omitted; a missing condition is always true.
+    fn for_statement(&mut self) -> crate::Result<Statement> {
+        self.eat(Token::LeftParen, "Expected '(' after 'for'")?;
+
+        let init = match self.peek() {
+            Token::Semicolon => None,
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.next();
+                if *self.peek() == Token::In {
+                    self.next();
+                    return Ok(Statement::ForIn(self.for_in_statement(name)?));
+                }
+                Some(self.assignment_to(name)?)
+            }
+            _ => Some(self.assignment()?),
+        };
+        self.eat(Token::Semicolon, "Expected ';' after for loop initializer")?;
+
+        let cond = match self.peek() {
+            Token::Semicolon => None,
+            _ => Some(self.expression(0)?),
+        };
+        self.eat(Token::Semicolon, "Expected ';' after for loop condition")?;
+
+        let step = match self.peek() {
+            Token::RightParen => None,
+            _ => Some(self.assignment()?),
+        };
+        self.eat(Token::RightParen, "Expected ')' after for loop step")?;
+
+        let body = self.loop_body("Expected '{' after for loop header")?;
+
+        Ok(Statement::For(ForStatement {
+            init,
+            cond,
+            step,
+            body,
+        }))
+    }
+
+    /// Parses the rest of a `for (key in arr)` statement, after the `in` keyword.
+    fn for_in_statement(&mut self, key: String) -> crate::Result<ForInStatement> {
+        if key == "now" {
+            return Err(compile_error("Cannot assign to 'now'", &self.current));
+        }
+        let array = match self.next() {
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.add_array(name)
+            }
+            tok => return Err(compile_error("Expected an array name after 'in'", tok)),
+        };
+        self.eat(Token::RightParen, "Expected ')' after array name")?;
+
+        let body = self.loop_body("Expected '{' after for loop header")?;
+
+        Ok(ForInStatement {
+            key: Variable::NotYetKnown(key),
+            array,
+            body,
+        })
+    }
+
+    fn loop_body(&mut self, msg: &str) -> crate::Result<Vec<Statement>> {
+        self.loop_depth += 1;
+        let body = self.block(msg);
+        self.loop_depth -= 1;
+        body
+    }
+
+    /// Parses a brace-enclosed list of statements, such as the body of an `if` statement.
+    fn block(&mut self, msg: &str) -> crate::Result<Vec<Statement>> {
+        self.eat(Token::LeftBrace, msg)?;
+
+        Ok(match self.peek() {
+            Token::RightBrace => {
+                self.next();
+                Vec::new()
+            }
+            _ => {
+                let statements = self.statements()?;
+                self.eat(Token::RightBrace, "Expected '}' after end of block")?;
+                statements
+            }
+        })
+    }
+
     fn compound_assignment(&mut self, var: Variable, tok: Token) -> crate::Result<Expression> {
         let kind = match tok {
             Token::PlusEqual => OpKind::Plus,
@@ -235,11 +525,25 @@ impl<'a> Compiler<'a> {
             // precedence so that subsequent operations at the same precedence level bind
             // to the left.
             let right = self.expression(Self::op_precedence(op) + 1)?;
-            left = Expression::Bin(BinaryOp {
-                kind: op,
-                left: Box::new(left),
-                right: Box::new(right),
-            });
+            left = match op {
+                OpKind::Match | OpKind::NotMatch | OpKind::Like | OpKind::ILike => {
+                    self.pattern_match(op, left, right)?
+                }
+                OpKind::EqualEqual | OpKind::NotEqual => {
+                    let right = self.resolve_name(&left, right);
+                    let left = self.resolve_name(&right, left);
+                    Expression::Bin(BinaryOp {
+                        kind: op,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    })
+                }
+                _ => Expression::Bin(BinaryOp {
+                    kind: op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                }),
+            };
 
             next = self.peek();
         }
@@ -247,17 +551,79 @@ impl<'a> Compiler<'a> {
         Ok(left)
     }
 
+    /// Builds a regex or glob match expression. When the pattern is a string literal, the regex is
+    /// compiled here, once, rather than every time the expression is evaluated. Otherwise, the
+    /// pattern is only known at runtime, so fall back to a binary operation that compiles it then.
+    fn pattern_match(
+        &self,
+        op: OpKind,
+        left: Expression,
+        right: Expression,
+    ) -> crate::Result<Expression> {
+        Ok(match right {
+            Expression::Atom(Value::String(pattern)) => {
+                let regex = compile_pattern(&pattern, op).map_err(|e| {
+                    compile_error(&format!("Invalid regular expression: {e}"), &self.current)
+                })?;
+                Expression::Match(RegexMatch {
+                    negated: op == OpKind::NotMatch,
+                    left: Box::new(left),
+                    regex,
+                })
+            }
+            right => Expression::Bin(BinaryOp {
+                kind: op,
+                left: Box::new(left),
+                right: Box::new(right),
+            }),
+        })
+    }
+
+    /// When `name` is a string literal compared to `.owner` or `.group`, as in `.owner == "alice"`,
+    /// looks up the user or group here, once, rather than every time the comparison is evaluated.
+    /// A name that doesn't exist is left as it is, so that, as for a name in a variable, the
+    /// comparison is false at runtime rather than the program failing to compile on hosts without
+    /// that user.
+    fn resolve_name(&self, other: &Expression, name: Expression) -> Expression {
+        let (Expression::Attr(attr), Expression::Atom(Value::String(s))) = (other, &name) else {
+            return name;
+        };
+
+        let id = match attr {
+            Attribute::Owner => crate::users::uid(s).map(SpecialValue::new_uid),
+            Attribute::Group => crate::users::gid(s).map(SpecialValue::new_gid),
+            _ => None,
+        };
+
+        match id {
+            Some(id) => Expression::Atom(id),
+            None => name,
+        }
+    }
+
     fn op_precedence(op: OpKind) -> u8 {
         match op {
             OpKind::Multiply => 50,
             OpKind::Divide => 50,
+            OpKind::Modulo => 50,
+            // As in Go, bitwise operators bind tighter than comparisons, so that
+            // `.mode & 0o777 == 0o644` does what it looks like:
+            OpKind::BitAnd => 50,
             OpKind::Plus => 40,
             OpKind::Minus => 40,
+            OpKind::BitOr => 40,
+            OpKind::BitXor => 40,
+            OpKind::Concat => 35,
             OpKind::Greater => 30,
             OpKind::GreaterEqual => 30,
             OpKind::Less => 30,
             OpKind::LessEqual => 30,
             OpKind::EqualEqual => 30,
+            OpKind::NotEqual => 30,
+            OpKind::Match => 30,
+            OpKind::NotMatch => 30,
+            OpKind::Like => 30,
+            OpKind::ILike => 30,
             OpKind::And => 20,
             OpKind::Or => 10,
         }
@@ -266,10 +632,31 @@ impl<'a> Compiler<'a> {
     fn factor(&mut self) -> crate::Result<Expression> {
         match self.next() {
             Token::Value(v) => Ok(Expression::Atom(v.clone())),
-            Token::Attr(a) => Ok(Expression::Attr(*a)),
+            Token::Attr(a) => Ok(match a.age_of() {
+                Some(timestamp) => Expression::Bin(BinaryOp {
+                    kind: OpKind::Minus,
+                    left: Box::new(Expression::Atom(self.now.clone())),
+                    right: Box::new(Expression::Attr(timestamp)),
+                }),
+                None => Expression::Attr(*a),
+            }),
             Token::Identifier(name) => {
                 let name = name.clone();
-                Ok(Expression::Var(self.variable(name)?))
+                match self.peek() {
+                    Token::LeftParen if name == "sprintf" => {
+                        let args = self.call(name)?.args;
+                        Ok(Expression::Sprintf(self.printf(args)?))
+                    }
+                    Token::LeftParen => match Builtin::from_name(&name) {
+                        Some(builtin) => {
+                            let args = self.call(name)?.args;
+                            Ok(Expression::Builtin(self.builtin(builtin, args)?))
+                        }
+                        None => Ok(Expression::Call(self.call(name)?)),
+                    },
+                    _ if name == "now" => Ok(Expression::Atom(self.now.clone())),
+                    _ => Ok(Expression::Var(self.variable(name)?)),
+                }
             }
             Token::LeftParen => {
                 let expr = self.expression(0)?;
@@ -279,10 +666,183 @@ impl<'a> Compiler<'a> {
                 )?;
                 Ok(expr)
             }
+            // `-` and `!` bind more tightly than any binary operator. `not` binds more loosely
+            // than comparisons, so that `not .size > 0` means `not (.size > 0)`, but more tightly
+            // than `and` and `or`.
+            Token::BinOp(OpKind::Minus) => {
+                let operand = self.factor()?;
+                Ok(match operand {
+                    // Fold negative integer literals rather than negating them at runtime:
+                    Expression::Atom(Value::Int(i)) => Expression::Atom(Value::Int(-i)),
+                    operand => Expression::Unary(UnaryOp {
+                        kind: UnaryKind::Negate,
+                        operand: Box::new(operand),
+                    }),
+                })
+            }
+            Token::Bang => Ok(Expression::Unary(UnaryOp {
+                kind: UnaryKind::Not,
+                operand: Box::new(self.factor()?),
+            })),
+            Token::Not => Ok(Expression::Unary(UnaryOp {
+                kind: UnaryKind::Not,
+                operand: Box::new(self.expression(Self::op_precedence(OpKind::EqualEqual))?),
+            })),
             t => Err(compile_error("Expected value, attribute, or identifier", t)),
         }
     }
 
+    /// Parses the argument list of a call to the function `name`.
+    fn call(&mut self, name: String) -> crate::Result<FunctionCall> {
+        self.eat(Token::LeftParen, "Expected '(' after function name")?;
+
+        let mut args = Vec::new();
+        if *self.peek() == Token::RightParen {
+            self.next();
+        } else {
+            loop {
+                args.push(self.expression(0)?);
+                match self.next() {
+                    Token::Comma => continue,
+                    Token::RightParen => break,
+                    tok => {
+                        return Err(compile_error(
+                            "Expected ',' or ')' after a function argument",
+                            tok,
+                        ))
+                    }
+                };
+            }
+        }
+
+        Ok(FunctionCall {
+            name,
+            id: None,
+            args,
+        })
+    }
+
+    /// Checks the arguments to a call to a builtin function.
+    fn builtin(
+        &mut self,
+        builtin: Builtin,
+        mut args: Vec<Expression>,
+    ) -> crate::Result<BuiltinCall> {
+        let (min, max) = builtin.arity();
+        if args.len() < min || args.len() > max {
+            let expected = if min == max {
+                format!("{min}")
+            } else if max == usize::MAX {
+                format!("at least {min}")
+            } else {
+                format!("{min} to {max}")
+            };
+            return Err(compile_error(
+                &format!(
+                    "{}() takes {expected} arguments, but got {}",
+                    builtin.name(),
+                    args.len()
+                ),
+                &self.current,
+            ));
+        }
+
+        let mut regex = None;
+        match builtin {
+            Builtin::Split => match &args[1] {
+                Expression::Var(Variable::NotYetKnown(name)) => {
+                    let id = self.add_array(name.clone());
+                    args[1] = Expression::Var(Variable::Arr(id));
+                }
+                _ => {
+                    return Err(compile_error(
+                        "The second argument to split() must be an array name",
+                        &self.current,
+                    ))
+                }
+            },
+            Builtin::Date => {
+                if let Expression::Atom(Value::String(date)) = &args[0] {
+                    if crate::time::parse_date(date).is_none() {
+                        return Err(compile_error(
+                            &format!("Invalid date \"{date}\""),
+                            &self.current,
+                        ));
+                    }
+                }
+            }
+            Builtin::Perm => {
+                if let Expression::Atom(Value::String(spec)) = &args[1] {
+                    if crate::perm::PermSpec::parse(spec).is_none() {
+                        return Err(compile_error(
+                            &format!("Invalid permission test \"{spec}\""),
+                            &self.current,
+                        ));
+                    }
+                }
+            }
+            Builtin::Hash => {
+                if let Expression::Atom(Value::String(algorithm)) = &args[1] {
+                    if crate::hash::Algorithm::from_name(algorithm).is_none() {
+                        return Err(compile_error(
+                            &format!("Unknown hash algorithm \"{algorithm}\""),
+                            &self.current,
+                        ));
+                    }
+                }
+            }
+            Builtin::Sub | Builtin::Gsub | Builtin::Contains | Builtin::GrepCount => {
+                let pattern = match builtin {
+                    Builtin::Sub | Builtin::Gsub => &args[0],
+                    _ => &args[1],
+                };
+                if let Expression::Atom(Value::String(pattern)) = pattern {
+                    regex = Some(regex::Regex::new(pattern).map_err(|e| {
+                        compile_error(&format!("Invalid regular expression: {e}"), &self.current)
+                    })?);
+                }
+            }
+            _ => {}
+        };
+
+        Ok(BuiltinCall {
+            builtin,
+            args,
+            regex,
+        })
+    }
+
+    /// Builds a `Printf` from the arguments to `printf` or `sprintf()`, the first of which is the
+    /// format. If the format is a string literal, it is parsed and checked against the number of
+    /// remaining arguments now, rather than at runtime.
+    fn printf(&self, mut args: Vec<Expression>) -> crate::Result<Printf> {
+        if args.is_empty() {
+            return Err(compile_error("Expected a format string", &self.current));
+        }
+
+        let format = match args.remove(0) {
+            Expression::Atom(Value::String(format)) => {
+                let format = Format::parse(&format).map_err(|e| {
+                    compile_error(&format!("Invalid format string: {e}"), &self.current)
+                })?;
+                if format.num_args() != args.len() {
+                    return Err(compile_error(
+                        &format!(
+                            "Format string expects {} arguments, but got {}",
+                            format.num_args(),
+                            args.len()
+                        ),
+                        &self.current,
+                    ));
+                }
+                FormatString::Compiled(format)
+            }
+            format => FormatString::Dynamic(Box::new(format)),
+        };
+
+        Ok(Printf { format, args })
+    }
+
     fn variable(&mut self, name: String) -> crate::Result<Variable> {
         Ok(match self.peek() {
             Token::LeftBracket => {
@@ -354,6 +914,119 @@ mod tests {
         should_error("{ print hey; .name");
     }
 
+    #[test]
+    fn if_statements() {
+        should_error("{ if 1 { print 1 } }");
+        should_error("{ if (1) print 1 }");
+        should_error("{ if (1 { print 1 } }");
+        should_error("{ if (1) { print 1 }");
+        should_error("{ if (1) { print 1 } else }");
+        should_error("{ if (1) { print 1 } else print 2 }");
+        should_error("{ if (1) { print 1 } else if { print 2 } }");
+        should_error("{ else { print 2 } }");
+        should_error("{ if (1) { print 1 } print 2 print 3 }");
+    }
+
+    #[test]
+    fn loops() {
+        should_error("{ while 1 { print 1 } }");
+        should_error("{ while (1) print 1 }");
+        should_error("{ while (1) { print 1 }");
+        should_error("{ for (i = 0; i < 3) { print i } }");
+        should_error("{ for (i = 0, i < 3, i += 1) { print i } }");
+        should_error("{ for (print 1;;) { print i } }");
+        should_error("{ for (;; print 1) { print i } }");
+        should_error("{ for (i;;) { print i } }");
+        should_error("{ break }");
+        should_error("{ continue }");
+        should_error("{ if (1) { break } }");
+        should_error("{ while (1) { } break }");
+        should_error("{ for (;;) { } continue }");
+        should_error("{ for (k in) { print k } }");
+        should_error("{ for (k in arr { print k } }");
+        should_error("{ for (k in arr[1]) { print k } }");
+        should_error("{ for (k in 1) { print k } }");
+        should_error("{ for (k[1] in arr) { print k } }");
+        should_error("{ arr[1] = 1; for (arr in arr) { print 1 } }");
+    }
+
+    #[test]
+    fn functions() {
+        should_error("function { }");
+        should_error("function f { }");
+        should_error("function f( { }");
+        should_error("function f(a b) { }");
+        should_error("function f(a,) { }");
+        should_error("function f(1) { }");
+        should_error("function f(a, a) { }");
+        should_error("function f() print 1");
+        should_error("function f() { } function f() { }");
+        should_error("{ return 1 }");
+        should_error("{ print f(1) }");
+        should_error("function f(a) { return a } { print f(1, 2) }");
+        should_error("function f(a) { return a } { print f(1 }");
+        should_error("function f(a) { return a } { print f(1 2) }");
+        should_error("function f(a) { a[1] = 2 }");
+    }
+
+    #[test]
+    fn printf() {
+        should_error("{ printf }");
+        should_error("{ printf \"%d\" }");
+        should_error("{ printf \"%d\", 1, 2 }");
+        should_error("{ printf \"%q\", 1 }");
+        should_error("{ printf \"%\" }");
+        should_error("{ print sprintf() }");
+        should_error("{ print sprintf(\"%s %s\", 1) }");
+        should_error("{ print sprintf(\"%s\", 1 }");
+        should_error("function sprintf(a) { return a }");
+    }
+
+    #[test]
+    fn builtins() {
+        should_error("{ print length() }");
+        should_error("{ print length(1, 2) }");
+        should_error("{ print substr(\"abc\") }");
+        should_error("{ print index(\"abc\", \"b\", 1) }");
+        should_error("{ print split(\"a b\") }");
+        should_error("{ print split(\"a b\", 1) }");
+        should_error("{ print split(\"a b\", arr[1]) }");
+        should_error("{ print gsub(\"(\", \"x\", .name) }");
+        should_error("{ print nosuchfunction(1) }");
+        should_error("function length(s) { return 1 }");
+        should_error("{ print \"a\" .. }");
+        should_error("{ print xattr(.path) }");
+        should_error("{ print hash(.path) }");
+        should_error("{ print hash(.path, \"crc32\") }");
+        should_error("{ print contains(.path) }");
+        should_error("{ print perm(.mode, \"u+q\") }");
+        should_error("{ print major(.dev, 1) }");
+        should_error("{ print grep_count(.path, \"(\") }");
+    }
+
+    #[test]
+    fn time() {
+        should_error("{ now = 1 }");
+        should_error("{ now += 1 }");
+        should_error("{ for (now in arr) { } }");
+        should_error("function f(now) { return now }");
+        should_error("{ print now() }");
+        should_error("{ print date(\"2024-13-01\") }");
+        should_error("{ print date(\"yesterday\") }");
+        should_error("{ print strftime(\"%Y\") }");
+    }
+
+    #[test]
+    fn unary_operators() {
+        should_error("-");
+        should_error("!");
+        should_error("not");
+        should_error("1 !");
+        should_error("1 not 2");
+        should_error("{ print - }");
+        should_error("{ print !() }");
+    }
+
     #[test]
     fn expressions() {
         should_error("+");
@@ -368,6 +1041,16 @@ mod tests {
         should_error("1 - ()");
     }
 
+    #[test]
+    fn regex_match() {
+        should_error(".name ~");
+        should_error(".name ~ \"(\"");
+        should_error(".name !~ \"[a-\"");
+        should_error("~ \"a\"");
+        should_error(".name like");
+        should_error("like \"*.log\"");
+    }
+
     #[test]
     fn arrays() {
         should_error("arr[1");
@@ -0,0 +1,73 @@
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// How many bytes at the start of a file are checked to decide what kind of file it is.
+const HEAD_LEN: u64 = 8192;
+
+/// Opens the file at `path` for reading at most `max_bytes` of it, if there is a limit.
+fn open(path: &Path, max_bytes: Option<u64>) -> std::io::Result<BufReader<impl Read>> {
+    let file = std::fs::File::open(path)?;
+    Ok(BufReader::new(file.take(max_bytes.unwrap_or(u64::MAX))))
+}
+
+/// Counts the lines in the file at `path`. A last line without a trailing newline is counted too.
+pub fn count_lines(path: &Path, max_bytes: Option<u64>) -> std::io::Result<u64> {
+    let mut reader = open(path, max_bytes)?;
+    let mut lines = 0;
+    let mut ends_with_newline = true;
+
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+
+        lines += buf.iter().filter(|b| **b == b'\n').count() as u64;
+        ends_with_newline = buf.last() == Some(&b'\n');
+
+        let len = buf.len();
+        reader.consume(len);
+    }
+
+    if !ends_with_newline {
+        lines += 1;
+    }
+
+    Ok(lines)
+}
+
+/// Reads the first few KB of the file at `path`, which is enough to tell what kind of file it is.
+pub fn read_head(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    open(path, Some(HEAD_LEN))?.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Guesses whether a file that starts with `head` is text rather than binary. Like git and grep, a
+/// file is taken to be binary if there is a NUL byte near its start.
+pub fn is_text(head: &[u8]) -> bool {
+    !head.contains(&0)
+}
+
+/// Counts the lines in the file at `path` that match `re`, like `grep -c`. If `first_only` is
+/// true, stops after the first match, so the result is 0 or 1.
+pub fn grep_count(
+    path: &Path,
+    re: &regex::Regex,
+    max_bytes: Option<u64>,
+    first_only: bool,
+) -> std::io::Result<u64> {
+    let mut count = 0;
+
+    for line in open(path, max_bytes)?.split(b'\n') {
+        // Lines that aren't valid UTF-8 can still match, in the parts that are:
+        if re.is_match(&String::from_utf8_lossy(&line?)) {
+            count += 1;
+            if first_only {
+                break;
+            }
+        }
+    }
+
+    Ok(count)
+}
@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+
+/// Names for the filesystem types that statfs(2) reports, by their magic numbers, from
+/// linux/magic.h. ext2, ext3 and ext4 share a magic number.
+const FS_TYPES: &[(i64, &str)] = &[
+    (0xef53, "ext4"),
+    (0x58465342, "xfs"),
+    (0x9123683e, "btrfs"),
+    (0x2fc12fc1, "zfs"),
+    (0xf2f52010, "f2fs"),
+    (0x3434, "nilfs"),
+    (0x52654973, "reiserfs"),
+    (0x4d44, "vfat"),
+    (0x2011bab0, "exfat"),
+    (0x5346544e, "ntfs"),
+    (0x9660, "iso9660"),
+    (0x73717368, "squashfs"),
+    (0x794c7630, "overlay"),
+    (0x01021994, "tmpfs"),
+    (0x858458f6, "ramfs"),
+    (0x958458f6, "hugetlbfs"),
+    (0x6969, "nfs"),
+    (0xff534d42, "cifs"),
+    (0xfe534d42, "smb2"),
+    (0x01021997, "9p"),
+    (0x65735546, "fuse"),
+    (0x9fa0, "proc"),
+    (0x62656572, "sysfs"),
+    (0x1cd1, "devpts"),
+    (0x27e0eb, "cgroup"),
+    (0x63677270, "cgroup2"),
+    (0x64626720, "debugfs"),
+    (0x74726163, "tracefs"),
+    (0x73636673, "securityfs"),
+    (0x6165676c, "pstore"),
+    (0xcafe4a11, "bpf"),
+    (0xde5e81e4, "efivarfs"),
+    (0x19800202, "mqueue"),
+    (0x42494e4d, "binfmt_misc"),
+    (0x6e736673, "nsfs"),
+    (0xf15f, "ecryptfs"),
+];
+
+/// Every file on a device is on the same filesystem, so the type is cached by device number for
+/// the whole process, rather than calling statfs() for every file.
+static FS_TYPE_CACHE: LazyLock<Mutex<HashMap<u64, String>>> = LazyLock::new(Default::default);
+
+/// The type of the filesystem that the file at `path`, on device `dev`, is on, such as "ext4".
+/// An unknown type is given as its magic number in hex.
+pub fn fs_type(path: &Path, dev: u64) -> std::io::Result<String> {
+    if let Some(fs_type) = FS_TYPE_CACHE.lock().unwrap().get(&dev) {
+        return Ok(fs_type.clone());
+    }
+
+    let magic = nix::sys::statfs::statfs(path)?.filesystem_type().0 as i64;
+    let fs_type = match FS_TYPES.iter().find(|(m, _)| *m == magic) {
+        Some((_, name)) => name.to_string(),
+        None => format!("{magic:#x}"),
+    };
+
+    FS_TYPE_CACHE.lock().unwrap().insert(dev, fs_type.clone());
+    Ok(fs_type)
+}
@@ -0,0 +1,275 @@
+use crate::types::*;
+use crate::RuntimeError;
+
+/// A parsed printf-style format string.
+///
+/// Supports the `%s`, `%d` (or `%i`), `%x`, `%X`, and `%o` conversions, each with optional `-`
+/// (left-justify) and `0` (zero-pad) flags, a minimum field width, and a precision, as well as
+/// `%%` for a literal percent sign. As in the shell's printf, the escape sequences `\n`, `\t`,
+/// `\\`, and `\"` are also interpreted.
+#[derive(Clone, Debug)]
+pub struct Format {
+    source: String,
+    pieces: Vec<Piece>,
+}
+
+#[derive(Clone, Debug)]
+enum Piece {
+    Literal(String),
+    Spec(Spec),
+}
+
+#[derive(Clone, Debug)]
+struct Spec {
+    left_justify: bool,
+    zero_pad: bool,
+    width: usize,
+    precision: Option<usize>,
+    conversion: Conversion,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Conversion {
+    String,
+    Decimal,
+    Hex,
+    UpperHex,
+    Octal,
+}
+
+impl Format {
+    pub fn parse(source: &str) -> Result<Format, String> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => match chars.next() {
+                    Some('n') => literal.push('\n'),
+                    Some('t') => literal.push('\t'),
+                    Some('\\') => literal.push('\\'),
+                    Some('"') => literal.push('"'),
+                    Some(ch) => {
+                        literal.push('\\');
+                        literal.push(ch);
+                    }
+                    None => literal.push('\\'),
+                },
+                '%' if chars.peek() == Some(&'%') => {
+                    chars.next();
+                    literal.push('%');
+                }
+                '%' => {
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Spec(Spec::parse(&mut chars)?));
+                }
+                ch => literal.push(ch),
+            }
+        }
+
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+
+        Ok(Format {
+            source: source.to_string(),
+            pieces,
+        })
+    }
+
+    /// The number of arguments that the format string consumes.
+    pub fn num_args(&self) -> usize {
+        self.pieces
+            .iter()
+            .filter(|p| matches!(p, Piece::Spec(_)))
+            .count()
+    }
+
+    pub fn format(&self, args: &[Value]) -> Result<String, RuntimeError> {
+        if args.len() != self.num_args() {
+            return Err(RuntimeError::new(&format!(
+                "Format string \"{}\" expects {} arguments, but got {}",
+                self.source,
+                self.num_args(),
+                args.len()
+            )));
+        }
+
+        let mut args = args.iter();
+        let mut s = String::new();
+        for piece in self.pieces.iter() {
+            match piece {
+                Piece::Literal(l) => s.push_str(l),
+                Piece::Spec(spec) => s.push_str(&spec.format(args.next().unwrap())?),
+            }
+        }
+
+        Ok(s)
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.source)
+    }
+}
+
+impl Spec {
+    /// Parse a conversion specification, starting just after the '%'.
+    fn parse(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Spec, String> {
+        let mut left_justify = false;
+        let mut zero_pad = false;
+        loop {
+            match chars.peek() {
+                Some('-') => left_justify = true,
+                Some('0') => zero_pad = true,
+                _ => break,
+            };
+            chars.next();
+        }
+
+        let width = Self::number(chars).unwrap_or(0);
+
+        let precision = match chars.peek() {
+            Some('.') => {
+                chars.next();
+                Some(Self::number(chars).unwrap_or(0))
+            }
+            _ => None,
+        };
+
+        let conversion = match chars.next() {
+            Some('s') => Conversion::String,
+            Some('d') | Some('i') => Conversion::Decimal,
+            Some('x') => Conversion::Hex,
+            Some('X') => Conversion::UpperHex,
+            Some('o') => Conversion::Octal,
+            Some(ch) => return Err(format!("Unknown format conversion '%{ch}'")),
+            None => return Err("Incomplete format conversion at end of string".to_string()),
+        };
+
+        Ok(Spec {
+            left_justify,
+            zero_pad,
+            width,
+            precision,
+            conversion,
+        })
+    }
+
+    fn number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<usize> {
+        let mut n = None;
+        while let Some(d) = chars.peek().and_then(|ch| ch.to_digit(10)) {
+            n = Some(n.unwrap_or(0) * 10 + d as usize);
+            chars.next();
+        }
+        n
+    }
+
+    fn format(&self, v: &Value) -> Result<String, RuntimeError> {
+        let (sign, digits) = match self.conversion {
+            Conversion::String => {
+                let s = v.to_string();
+                let s = match self.precision {
+                    Some(p) => s.chars().take(p).collect(),
+                    None => s,
+                };
+                return Ok(self.pad("", s, false));
+            }
+            Conversion::Decimal => {
+                let i = Self::integer(v)?;
+                (if i < 0 { "-" } else { "" }, i.unsigned_abs().to_string())
+            }
+            // Like C, negative numbers are shown as their two's complement:
+            Conversion::Hex => ("", format!("{:x}", Self::integer(v)? as u64)),
+            Conversion::UpperHex => ("", format!("{:X}", Self::integer(v)? as u64)),
+            Conversion::Octal => ("", format!("{:o}", Self::integer(v)? as u64)),
+        };
+
+        // For integers, the precision is the minimum number of digits:
+        let digits = match self.precision {
+            Some(p) if digits.len() < p => format!("{}{digits}", "0".repeat(p - digits.len())),
+            _ => digits,
+        };
+
+        Ok(self.pad(sign, digits, self.zero_pad && self.precision.is_none()))
+    }
+
+    /// Special values like inode numbers can't be used in arithmetic, but it's still useful to
+    /// be able to print them as numbers.
+    fn integer(v: &Value) -> Result<i128, RuntimeError> {
+        Ok(match v {
+            Value::Special(s) => s.raw(),
+            v => v.clone().to_signed_int()?.into(),
+        })
+    }
+
+    /// Pads `sign` followed by `body` out to the field width. Zero padding goes between the sign
+    /// and the body.
+    fn pad(&self, sign: &str, body: String, zero_pad: bool) -> String {
+        let len = sign.len() + body.chars().count();
+        if len >= self.width {
+            return format!("{sign}{body}");
+        }
+
+        let fill = self.width - len;
+        if self.left_justify {
+            format!("{sign}{body}{}", " ".repeat(fill))
+        } else if zero_pad {
+            format!("{sign}{}{body}", "0".repeat(fill))
+        } else {
+            format!("{}{sign}{body}", " ".repeat(fill))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(fmt: &str, args: &[Value]) -> String {
+        Format::parse(fmt).unwrap().format(args).unwrap()
+    }
+
+    #[test]
+    fn conversions() {
+        let s = Value::String("hey".to_string());
+        assert_eq!(format("%s!", std::slice::from_ref(&s)), "hey!");
+        assert_eq!(format("%d %i", &[Value::Int(-3), Value::Int(4)]), "-3 4");
+        assert_eq!(
+            format("%x %X", &[Value::Int(255), Value::Int(255)]),
+            "ff FF"
+        );
+        assert_eq!(format("%o", &[Value::Int(8)]), "10");
+        assert_eq!(format("100%%", &[]), "100%");
+        assert_eq!(format("a\\tb\\n", &[]), "a\tb\n");
+    }
+
+    #[test]
+    fn width_and_precision() {
+        let s = Value::String("hey".to_string());
+        assert_eq!(format("[%5s]", std::slice::from_ref(&s)), "[  hey]");
+        assert_eq!(format("[%-5s]", std::slice::from_ref(&s)), "[hey  ]");
+        assert_eq!(format("[%.2s]", std::slice::from_ref(&s)), "[he]");
+        assert_eq!(format("[%5d]", &[Value::Int(42)]), "[   42]");
+        assert_eq!(format("[%-5d]", &[Value::Int(42)]), "[42   ]");
+        assert_eq!(format("[%05d]", &[Value::Int(-42)]), "[-0042]");
+        assert_eq!(format("[%.4d]", &[Value::Int(42)]), "[0042]");
+        assert_eq!(format("[%6.4x]", &[Value::Int(255)]), "[  00ff]");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Format::parse("%").is_err());
+        assert!(Format::parse("%5").is_err());
+        assert!(Format::parse("%q").is_err());
+        assert!(Format::parse("%d").unwrap().format(&[]).is_err());
+        assert!(Format::parse("%d")
+            .unwrap()
+            .format(&[Value::Int(1), Value::Int(2)])
+            .is_err());
+    }
+}
@@ -0,0 +1,114 @@
+/// Translates a shell-style glob into an equivalent, anchored regular expression.
+///
+/// Supported syntax:
+/// - `*` matches any run of characters other than `/`
+/// - `**` matches any run of characters, including `/`; `**/` also matches zero directories
+/// - `?` matches any single character other than `/`
+/// - `[...]` matches a character class, negated by a leading `!` or `^`
+///
+/// An unterminated `[` is treated as a literal character, like the shell does.
+pub fn to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut re = String::from("^");
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    re.push_str("(?:.*/)?");
+                    i += 1;
+                } else {
+                    re.push_str(".*");
+                }
+                i += 1;
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            '[' => match class(&chars[i + 1..]) {
+                Some((class, len)) => {
+                    re.push_str(&class);
+                    i += len;
+                }
+                None => re.push_str(r"\["),
+            },
+            ch => re.push_str(&regex::escape(ch.encode_utf8(&mut [0; 4]))),
+        }
+        i += 1;
+    }
+
+    re.push('$');
+    re
+}
+
+/// Translates the body of a bracket expression, starting just after the opening `[`, into a regex
+/// character class.
+///
+/// Returns the class along with the number of glob characters consumed, including the closing
+/// `]`, or None if the class is never closed.
+fn class(chars: &[char]) -> Option<(String, usize)> {
+    let mut re = String::from("[");
+    let mut i = 0;
+
+    if let Some('!' | '^') = chars.first() {
+        re.push('^');
+        i += 1;
+    }
+
+    // A ']' right at the start of the class is a literal rather than the end of the class:
+    let start = i;
+    loop {
+        match chars.get(i)? {
+            ']' if i > start => break,
+            '-' => re.push('-'),
+            ch => re.push_str(&regex::escape(ch.encode_utf8(&mut [0; 4]))),
+        }
+        i += 1;
+    }
+
+    re.push(']');
+    Some((re, i + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(glob: &str, s: &str) -> bool {
+        regex::Regex::new(&to_regex(glob)).unwrap().is_match(s)
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*.log", "a.log"));
+        assert!(matches("*.log", ".log"));
+        assert!(!matches("*.log", "a.log.1"));
+        assert!(!matches("*.log", "dir/a.log"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "a/c"));
+        assert!(!matches("a?c", "ac"));
+        assert!(matches("a.c", "a.c"));
+        assert!(!matches("a.c", "abc"));
+    }
+
+    #[test]
+    fn double_star() {
+        assert!(matches("**/*.rs", "src/ast/analysis.rs"));
+        assert!(matches("**/*.rs", "lib.rs"));
+        assert!(matches("src/**", "src/ast/analysis.rs"));
+        assert!(matches("src/**/mod.rs", "src/mod.rs"));
+        assert!(!matches("src/**/mod.rs", "lib/a/mod.rs"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(matches("file[0-9]", "file7"));
+        assert!(!matches("file[0-9]", "filex"));
+        assert!(matches("file[!0-9]", "filex"));
+        assert!(matches("file[^0-9]", "filex"));
+        assert!(!matches("file[!0-9]", "file7"));
+        assert!(matches("[]a]", "]"));
+        assert!(matches("[[]", "["));
+        assert!(matches("a[", "a["));
+    }
+}
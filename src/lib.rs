@@ -1,10 +1,22 @@
+pub mod access;
 pub mod ast;
 pub mod compiler;
+pub mod content;
+pub mod filesystem;
+pub mod format;
+pub mod glob;
+pub mod hash;
+pub mod magic;
+pub mod metadata;
+pub mod perm;
 pub mod program_state;
 pub mod scanner;
+pub mod time;
 pub mod treewalk;
 pub mod types;
+pub mod users;
 pub mod variables;
+pub mod xattr;
 
 pub mod test_libs;
 
@@ -19,6 +31,15 @@ pub struct Args {
     pub path: std::path::PathBuf,
     pub prog: String,
     pub n_threads: usize,
+    /// Follow symlinks, both when getting a file's metadata and when walking the tree. A symlink
+    /// given as `path` is followed either way.
+    pub follow: bool,
+    /// The most bytes of a file's contents to read for `.lines`, `contains()` and `grep_count()`,
+    /// or None to read all of it.
+    pub max_bytes: Option<u64>,
+    /// Check `.readable`, `.writable` and `.executable` with the effective user and group IDs,
+    /// rather than the real ones.
+    pub effective_ids: bool,
 }
 
 pub fn driver<T: crate::SyncWrite>(args: &crate::Args, out: &mut T) -> Result<()> {
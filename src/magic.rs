@@ -0,0 +1,211 @@
+/// A kind of file that can be recognized by its contents, as reported by `.mime` and `.magic`.
+#[derive(Debug, PartialEq)]
+pub struct Magic {
+    pub mime: &'static str,
+    pub description: String,
+}
+
+/// A signature to recognize: every byte string has to appear at its offset.
+struct Signature {
+    parts: &'static [(usize, &'static [u8])],
+    mime: &'static str,
+    description: &'static str,
+}
+
+const fn sig(
+    parts: &'static [(usize, &'static [u8])],
+    mime: &'static str,
+    description: &'static str,
+) -> Signature {
+    Signature {
+        parts,
+        mime,
+        description,
+    }
+}
+
+/// The signatures, checked in order. The first one that matches wins.
+const SIGNATURES: &[Signature] = &[
+    // Executables and libraries:
+    sig(&[(0, b"\x7fELF")], "application/x-executable", "ELF"),
+    sig(
+        &[(0, b"\xfe\xed\xfa\xce")],
+        "application/x-mach-binary",
+        "Mach-O",
+    ),
+    sig(
+        &[(0, b"\xfe\xed\xfa\xcf")],
+        "application/x-mach-binary",
+        "Mach-O",
+    ),
+    sig(
+        &[(0, b"\xce\xfa\xed\xfe")],
+        "application/x-mach-binary",
+        "Mach-O",
+    ),
+    sig(
+        &[(0, b"\xcf\xfa\xed\xfe")],
+        "application/x-mach-binary",
+        "Mach-O",
+    ),
+    sig(
+        &[(0, b"MZ")],
+        "application/vnd.microsoft.portable-executable",
+        "PE executable",
+    ),
+    sig(&[(0, b"\0asm")], "application/wasm", "WebAssembly"),
+    // Images:
+    sig(&[(0, b"\x89PNG\r\n\x1a\n")], "image/png", "PNG image"),
+    sig(&[(0, b"\xff\xd8\xff")], "image/jpeg", "JPEG image"),
+    sig(&[(0, b"GIF87a")], "image/gif", "GIF image"),
+    sig(&[(0, b"GIF89a")], "image/gif", "GIF image"),
+    sig(&[(0, b"RIFF"), (8, b"WEBP")], "image/webp", "WebP image"),
+    // Audio and video:
+    sig(&[(0, b"ID3")], "audio/mpeg", "MP3 audio"),
+    sig(&[(0, b"OggS")], "audio/ogg", "Ogg audio"),
+    sig(&[(0, b"fLaC")], "audio/flac", "FLAC audio"),
+    sig(&[(0, b"RIFF"), (8, b"WAVE")], "audio/wav", "WAV audio"),
+    sig(&[(4, b"ftyp")], "video/mp4", "MP4 video"),
+    // Documents:
+    sig(&[(0, b"%PDF-")], "application/pdf", "PDF document"),
+    sig(
+        &[(0, b"SQLite format 3\0")],
+        "application/vnd.sqlite3",
+        "SQLite database",
+    ),
+    // Archives and compressed data:
+    sig(
+        &[(0, b"\x1f\x8b")],
+        "application/gzip",
+        "gzip compressed data",
+    ),
+    sig(
+        &[(0, b"BZh")],
+        "application/x-bzip2",
+        "bzip2 compressed data",
+    ),
+    sig(
+        &[(0, b"\xfd7zXZ\0")],
+        "application/x-xz",
+        "XZ compressed data",
+    ),
+    sig(
+        &[(0, b"\x28\xb5\x2f\xfd")],
+        "application/zstd",
+        "Zstandard compressed data",
+    ),
+    sig(&[(0, b"PK\x03\x04")], "application/zip", "Zip archive"),
+    sig(&[(0, b"PK\x05\x06")], "application/zip", "Zip archive"),
+    sig(
+        &[(0, b"7z\xbc\xaf\x27\x1c")],
+        "application/x-7z-compressed",
+        "7-zip archive",
+    ),
+    sig(&[(257, b"ustar")], "application/x-tar", "tar archive"),
+    // Text formats:
+    sig(&[(0, b"<?xml")], "text/xml", "XML document"),
+];
+
+impl Signature {
+    fn matches(&self, head: &[u8]) -> bool {
+        self.parts
+            .iter()
+            .all(|(offset, bytes)| head.get(*offset..).is_some_and(|h| h.starts_with(bytes)))
+    }
+}
+
+/// Works out what kind of file starts with `head`. A file that matches no signature is described
+/// as a script if it starts with `#!`, otherwise as text or data.
+pub fn identify(head: &[u8]) -> Magic {
+    if let Some(sig) = SIGNATURES.iter().find(|sig| sig.matches(head)) {
+        return Magic {
+            mime: sig.mime,
+            description: sig.description.to_string(),
+        };
+    }
+
+    if let Some(interpreter) = interpreter(head) {
+        let mime = match interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+            "sh" | "bash" | "dash" | "ksh" | "zsh" => "text/x-shellscript",
+            "python" => "text/x-script.python",
+            "perl" => "text/x-perl",
+            "ruby" => "text/x-ruby",
+            "node" => "application/javascript",
+            _ => "text/x-script",
+        };
+        return Magic {
+            mime,
+            description: format!("{interpreter} script"),
+        };
+    }
+
+    let (mime, description) = if head.is_empty() {
+        ("inode/x-empty", "empty")
+    } else if crate::content::is_text(head) {
+        ("text/plain", "text")
+    } else {
+        ("application/octet-stream", "data")
+    };
+    Magic {
+        mime,
+        description: description.to_string(),
+    }
+}
+
+/// The name of the program in a `#!` line, such as "python3" for `#!/usr/bin/env python3`.
+fn interpreter(head: &[u8]) -> Option<String> {
+    let line = head.strip_prefix(b"#!")?.split(|b| *b == b'\n').next()?;
+    let line = std::str::from_utf8(line).ok()?;
+
+    let mut words = line.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|w| !w.starts_with('-'))?;
+    }
+
+    Some(program.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mime(head: &[u8]) -> &'static str {
+        identify(head).mime
+    }
+
+    #[test]
+    fn signatures() {
+        assert_eq!(mime(b"\x7fELF\x02\x01\x01\0"), "application/x-executable");
+        assert_eq!(mime(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), "image/png");
+        assert_eq!(mime(b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+        assert_eq!(mime(b"RIFF\0\0\0\0WAVEfmt "), "audio/wav");
+        assert_eq!(mime(b"RIFF"), "text/plain");
+        assert_eq!(mime(b"\x1f\x8b\x08\0"), "application/gzip");
+
+        let mut tar = vec![0; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(mime(&tar), "application/x-tar");
+    }
+
+    #[test]
+    fn fallbacks() {
+        assert_eq!(identify(b"").description, "empty");
+        assert_eq!(identify(b"hello\n").description, "text");
+        assert_eq!(identify(b"\x01\0\x02").description, "data");
+    }
+
+    #[test]
+    fn scripts() {
+        let magic = identify(b"#!/bin/sh\necho hi\n");
+        assert_eq!(magic.mime, "text/x-shellscript");
+        assert_eq!(magic.description, "sh script");
+
+        let magic = identify(b"#!/usr/bin/env -S python3 -u\n");
+        assert_eq!(magic.mime, "text/x-script.python");
+        assert_eq!(magic.description, "python3 script");
+
+        assert_eq!(mime(b"#!/usr/bin/awk -f\n"), "text/x-script");
+        assert_eq!(mime(b"#!\n"), "text/plain");
+    }
+}
@@ -13,6 +13,18 @@ struct RawArgs {
     /// Number of threads.
     #[arg(short = 'j', long, default_value_t = 4)]
     n_threads: usize,
+
+    /// Follow symbolic links. A symbolic link given as the path is always followed.
+    #[arg(short = 'L', long)]
+    follow: bool,
+
+    /// Read at most this many bytes of each file when looking at its contents.
+    #[arg(long)]
+    max_bytes: Option<u64>,
+
+    /// Check access with the effective user and group IDs rather than the real ones.
+    #[arg(long)]
+    effective: bool,
 }
 
 fn main() {
@@ -32,6 +44,9 @@ fn main() {
         path,
         prog: prog.to_string(),
         n_threads: raw_args.n_threads,
+        follow: raw_args.follow,
+        max_bytes: raw_args.max_bytes,
+        effective_ids: raw_args.effective,
     };
 
     match puffin::driver(&args, &mut std::io::stdout()) {
@@ -0,0 +1,252 @@
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use crate::RuntimeError;
+
+/// File metadata, as returned by statx(2).
+///
+/// Compared to `std::fs::Metadata`, this also has the fields that only statx() provides, such as
+/// the birth time and the mount ID. Not every filesystem supports those, so the fields that were
+/// actually filled in are recorded in the mask, and asking for one that wasn't is an error.
+pub struct Metadata {
+    stx: libc::statx,
+}
+
+/// The fields we ask statx() for.
+const MASK: u32 = libc::STATX_BASIC_STATS | libc::STATX_BTIME | libc::STATX_MNT_ID;
+
+/// The statx() fields reported by `.statx_mask`, and their names.
+const FIELDS: &[(u32, &str)] = &[
+    (libc::STATX_TYPE, "type"),
+    (libc::STATX_MODE, "mode"),
+    (libc::STATX_NLINK, "nlink"),
+    (libc::STATX_UID, "uid"),
+    (libc::STATX_GID, "gid"),
+    (libc::STATX_ATIME, "atime"),
+    (libc::STATX_MTIME, "mtime"),
+    (libc::STATX_CTIME, "ctime"),
+    (libc::STATX_INO, "ino"),
+    (libc::STATX_SIZE, "size"),
+    (libc::STATX_BLOCKS, "blocks"),
+    (libc::STATX_BTIME, "btime"),
+    (libc::STATX_MNT_ID, "mnt_id"),
+];
+
+/// The file attributes reported by `.attributes`, and their names.
+const ATTRIBUTES: &[(i32, &str)] = &[
+    (libc::STATX_ATTR_IMMUTABLE, "immutable"),
+    (libc::STATX_ATTR_APPEND, "append"),
+    (libc::STATX_ATTR_COMPRESSED, "compressed"),
+    (libc::STATX_ATTR_ENCRYPTED, "encrypted"),
+    (libc::STATX_ATTR_NODUMP, "nodump"),
+    (libc::STATX_ATTR_VERITY, "verity"),
+    (libc::STATX_ATTR_DAX, "dax"),
+];
+
+impl Metadata {
+    /// Get the metadata for `path`, following symlinks.
+    pub fn statx(path: &Path) -> std::io::Result<Metadata> {
+        Self::statx_flags(path, libc::AT_STATX_SYNC_AS_STAT)
+    }
+
+    /// Get the metadata for `path`, without following symlinks, like lstat(2).
+    pub fn lstatx(path: &Path) -> std::io::Result<Metadata> {
+        Self::statx_flags(
+            path,
+            libc::AT_STATX_SYNC_AS_STAT | libc::AT_SYMLINK_NOFOLLOW,
+        )
+    }
+
+    /// Get the metadata for `path`, following symlinks. If the path is a broken symlink, get the
+    /// metadata of the link itself instead, like `find -L` does.
+    pub fn follow(path: &Path) -> std::io::Result<Metadata> {
+        Self::statx(path).or_else(|e| match e.raw_os_error() {
+            Some(libc::ENOENT) | Some(libc::ELOOP) => Self::lstatx(path),
+            _ => Err(e),
+        })
+    }
+
+    fn statx_flags(path: &Path, flags: i32) -> std::io::Result<Metadata> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+
+        // SAFETY: an all-zero statx struct is valid, since it's plain integers.
+        let mut stx: libc::statx = unsafe { std::mem::zeroed() };
+        // SAFETY: `path` is a valid C string, and `stx` is a valid statx struct to write to.
+        let res = unsafe { libc::statx(libc::AT_FDCWD, path.as_ptr(), flags, MASK, &mut stx) };
+
+        match res {
+            0 => Ok(Metadata { stx }),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    }
+
+    /// The statx() mask of which fields were filled in.
+    pub fn mask(&self) -> u32 {
+        self.stx.stx_mask
+    }
+
+    /// The names of the fields that statx() filled in, such as "btime", separated by commas.
+    pub fn mask_names(&self) -> String {
+        FIELDS
+            .iter()
+            .filter(|(field, _)| self.mask() & field != 0)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Checks that the field `field`, called `name`, was filled in by statx().
+    fn require(&self, field: u32, name: &str) -> Result<(), RuntimeError> {
+        match self.mask() & field {
+            0 => Err(RuntimeError::new(&format!(
+                "The filesystem does not report the {name} of this file"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn blksize(&self) -> u64 {
+        self.stx.stx_blksize.into()
+    }
+
+    pub fn blocks(&self) -> u64 {
+        self.stx.stx_blocks
+    }
+
+    pub fn dev(&self) -> u64 {
+        libc::makedev(self.stx.stx_dev_major, self.stx.stx_dev_minor)
+    }
+
+    pub fn ino(&self) -> u64 {
+        self.stx.stx_ino
+    }
+
+    pub fn mode(&self) -> u32 {
+        self.stx.stx_mode.into()
+    }
+
+    pub fn nlink(&self) -> u64 {
+        self.stx.stx_nlink.into()
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.stx.stx_uid
+    }
+
+    pub fn gid(&self) -> u32 {
+        self.stx.stx_gid
+    }
+
+    pub fn rdev(&self) -> u64 {
+        libc::makedev(self.stx.stx_rdev_major, self.stx.stx_rdev_minor)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.stx.stx_size
+    }
+
+    pub fn atime(&self) -> i64 {
+        self.stx.stx_atime.tv_sec
+    }
+
+    pub fn atime_nsec(&self) -> i64 {
+        self.stx.stx_atime.tv_nsec.into()
+    }
+
+    pub fn mtime(&self) -> i64 {
+        self.stx.stx_mtime.tv_sec
+    }
+
+    pub fn mtime_nsec(&self) -> i64 {
+        self.stx.stx_mtime.tv_nsec.into()
+    }
+
+    pub fn ctime(&self) -> i64 {
+        self.stx.stx_ctime.tv_sec
+    }
+
+    pub fn ctime_nsec(&self) -> i64 {
+        self.stx.stx_ctime.tv_nsec.into()
+    }
+
+    /// The birth time, as seconds and nanoseconds.
+    pub fn btime(&self) -> Result<(i64, i64), RuntimeError> {
+        self.require(libc::STATX_BTIME, "birth time")?;
+        Ok((self.stx.stx_btime.tv_sec, self.stx.stx_btime.tv_nsec.into()))
+    }
+
+    /// The ID of the mount containing the file, as in /proc/self/mountinfo.
+    pub fn mnt_id(&self) -> Result<u64, RuntimeError> {
+        self.require(libc::STATX_MNT_ID, "mount ID")?;
+        Ok(self.stx.stx_mnt_id)
+    }
+
+    /// The names of the file attributes that are set, such as "immutable", separated by commas.
+    ///
+    /// An attribute that the filesystem doesn't support, according to the attributes mask, is
+    /// left out the same as one that isn't set, so an empty list doesn't mean that a file is
+    /// definitely not, say, immutable.
+    pub fn attributes(&self) -> String {
+        let set = self.stx.stx_attributes & self.stx.stx_attributes_mask;
+        ATTRIBUTES
+            .iter()
+            .filter(|(attr, _)| set & (*attr as u64) != 0)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Whether the file is the root of a mount, or None if the kernel is too old to say.
+    pub fn mount_root(&self) -> Option<bool> {
+        let attr = libc::STATX_ATTR_MOUNT_ROOT as u64;
+        match self.stx.stx_attributes_mask & attr {
+            0 => None,
+            _ => Some(self.stx.stx_attributes & attr != 0),
+        }
+    }
+
+    fn file_type(&self) -> u32 {
+        self.mode() & libc::S_IFMT
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.file_type() == libc::S_IFDIR
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.file_type() == libc::S_IFREG
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.file_type() == libc::S_IFLNK
+    }
+
+    pub fn is_block_device(&self) -> bool {
+        self.file_type() == libc::S_IFBLK
+    }
+
+    pub fn is_char_device(&self) -> bool {
+        self.file_type() == libc::S_IFCHR
+    }
+
+    pub fn is_fifo(&self) -> bool {
+        self.file_type() == libc::S_IFIFO
+    }
+
+    pub fn is_socket(&self) -> bool {
+        self.file_type() == libc::S_IFSOCK
+    }
+}
+
+/// The major part of the device number `dev`, identifying the driver.
+pub fn major(dev: u64) -> u32 {
+    // SAFETY: major() is only arithmetic on its argument.
+    unsafe { libc::major(dev) }
+}
+
+/// The minor part of the device number `dev`, identifying the device to its driver.
+pub fn minor(dev: u64) -> u32 {
+    // SAFETY: minor() is only arithmetic on its argument.
+    unsafe { libc::minor(dev) }
+}
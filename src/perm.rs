@@ -0,0 +1,240 @@
+/// A test of a file's permission bits, as taken by `perm()`. The syntax is based on chmod(1) and
+/// `find -perm`:
+///
+/// - An octal number, such as "644", matches if the permission bits are exactly that.
+/// - Symbolic clauses separated by commas, such as "o+w" or "u=rwx,go-w", match if every clause
+///   holds: `+` means all of the given bits are set, `-` that none of them are, and `=` that the
+///   bits for those users are exactly the given ones. With no users given, as in "+x", the clause
+///   is about all users.
+/// - "-" followed by either of those, such as "-022" or "-g+w,o+w", matches if all of the given
+///   bits are set, and "/" matches if any of them are.
+#[derive(Debug, PartialEq)]
+pub enum PermSpec {
+    Exact(u32),
+    Symbolic(Vec<Clause>),
+    All(u32),
+    Any(u32),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Clause {
+    /// All of the bits that belong to the users the clause is about
+    who: u32,
+    op: char,
+    /// The bits given in the clause, for those users
+    perms: u32,
+}
+
+/// The user, group and other bits for each permission character.
+const PERMS: &[(char, [u32; 3])] = &[
+    ('r', [0o400, 0o040, 0o004]),
+    ('w', [0o200, 0o020, 0o002]),
+    ('x', [0o100, 0o010, 0o001]),
+    ('s', [0o4000, 0o2000, 0]),
+    ('t', [0, 0, 0o1000]),
+];
+
+impl PermSpec {
+    /// Parses a permission test, returning None if it isn't valid.
+    pub fn parse(spec: &str) -> Option<PermSpec> {
+        if let Some(spec) = spec.strip_prefix('-') {
+            return Some(PermSpec::All(Self::parse_bits(spec)?));
+        }
+        if let Some(spec) = spec.strip_prefix('/') {
+            return Some(PermSpec::Any(Self::parse_bits(spec)?));
+        }
+
+        match parse_octal(spec) {
+            Some(bits) => Some(PermSpec::Exact(bits)),
+            None => Some(PermSpec::Symbolic(parse_clauses(spec)?)),
+        }
+    }
+
+    /// Parses the bits after a `-` or `/`, which can be octal or symbolic. Symbolic clauses can
+    /// only add bits, since it makes no sense to test whether bits are set by taking them away.
+    fn parse_bits(spec: &str) -> Option<u32> {
+        if let Some(bits) = parse_octal(spec) {
+            return Some(bits);
+        }
+
+        parse_clauses(spec)?
+            .iter()
+            .try_fold(0, |bits, clause| match clause.op {
+                '-' => None,
+                _ => Some(bits | clause.perms),
+            })
+    }
+
+    /// Whether the mode `mode` passes the test.
+    pub fn matches(&self, mode: u32) -> bool {
+        match self {
+            PermSpec::Exact(bits) => mode & 0o7777 == *bits,
+            PermSpec::All(bits) => mode & bits == *bits,
+            // As with find, testing whether any of no bits are set is always true:
+            PermSpec::Any(bits) => *bits == 0 || mode & bits != 0,
+            PermSpec::Symbolic(clauses) => clauses.iter().all(|clause| match clause.op {
+                '+' => mode & clause.perms == clause.perms,
+                '-' => mode & clause.perms == 0,
+                _ => mode & clause.who == clause.perms,
+            }),
+        }
+    }
+}
+
+fn parse_octal(spec: &str) -> Option<u32> {
+    if spec.is_empty() || !spec.chars().all(|c| c.is_digit(8)) {
+        return None;
+    }
+
+    u32::from_str_radix(spec, 8)
+        .ok()
+        .filter(|bits| *bits <= 0o7777)
+}
+
+/// Parses comma-separated symbolic clauses such as "u+x,go-w". Like chmod, a clause can have more
+/// than one operator, as in "u+r-w".
+fn parse_clauses(spec: &str) -> Option<Vec<Clause>> {
+    let mut clauses = Vec::new();
+
+    for clause in spec.split(',') {
+        let ops = clause.find(['+', '-', '='])?;
+        let (who, mut rest) = clause.split_at(ops);
+
+        // Which of user, group and other the clause is about:
+        let mut classes = [false; 3];
+        for c in who.chars() {
+            match c {
+                'u' => classes[0] = true,
+                'g' => classes[1] = true,
+                'o' => classes[2] = true,
+                'a' => classes = [true; 3],
+                _ => return None,
+            }
+        }
+        if who.is_empty() {
+            classes = [true; 3];
+        }
+
+        let bits_for = |c: char| -> Option<u32> {
+            let (_, bits) = PERMS.iter().find(|(p, _)| *p == c)?;
+            Some(
+                (0..3)
+                    .filter(|i| classes[*i])
+                    .fold(0, |acc, i| acc | bits[i]),
+            )
+        };
+        let who = PERMS
+            .iter()
+            .filter_map(|(c, _)| bits_for(*c))
+            .fold(0, |acc, bits| acc | bits);
+
+        while let Some(op) = rest.chars().next() {
+            let perms_end = rest[1..]
+                .find(['+', '-', '='])
+                .map_or(rest.len(), |i| i + 1);
+            let perms = rest[1..perms_end]
+                .chars()
+                .try_fold(0, |acc, c| Some(acc | bits_for(c)?))?;
+
+            clauses.push(Clause { who, op, perms });
+            rest = &rest[perms_end..];
+        }
+    }
+
+    Some(clauses)
+}
+
+/// Renders a mode the way `ls -l` does, such as "-rwxr-xr-x" for an executable file or
+/// "drwxrwxrwt" for /tmp.
+pub fn render(mode: u32) -> String {
+    let file_type = match mode & libc::S_IFMT {
+        libc::S_IFREG => '-',
+        libc::S_IFDIR => 'd',
+        libc::S_IFLNK => 'l',
+        libc::S_IFBLK => 'b',
+        libc::S_IFCHR => 'c',
+        libc::S_IFIFO => 'p',
+        libc::S_IFSOCK => 's',
+        _ => '?',
+    };
+
+    let mut s = String::from(file_type);
+    // The read, write and execute bits for each class, plus the special bit shown in place of
+    // its execute bit, and how that looks with and without the execute bit:
+    for (shift, special, chars) in [
+        (6, 0o4000, ['s', 'S']),
+        (3, 0o2000, ['s', 'S']),
+        (0, 0o1000, ['t', 'T']),
+    ] {
+        let bits = (mode >> shift) & 0o7;
+        s.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        s.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        s.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => chars[0],
+            (false, true) => chars[1],
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(spec: &str, mode: u32) -> bool {
+        PermSpec::parse(spec).unwrap().matches(mode)
+    }
+
+    #[test]
+    fn exact() {
+        assert!(matches("644", 0o100644));
+        assert!(!matches("644", 0o100664));
+        assert!(matches("4755", 0o104755));
+    }
+
+    #[test]
+    fn symbolic() {
+        assert!(matches("o+w", 0o100666));
+        assert!(!matches("o+w", 0o100664));
+        assert!(matches("g+x,o-w", 0o100750));
+        assert!(matches("u=rw,go=r", 0o100644));
+        assert!(!matches("u=rw,go=r", 0o100640));
+        assert!(matches("+r", 0o100444));
+        assert!(!matches("+r", 0o100440));
+        assert!(matches("u+s", 0o104755));
+        assert!(matches("o+t", 0o41777));
+        assert!(matches("u+r-x", 0o100644));
+        assert!(matches("a=", 0o100000));
+    }
+
+    #[test]
+    fn all_and_any() {
+        assert!(matches("-022", 0o100666));
+        assert!(!matches("-022", 0o100646));
+        assert!(matches("/022", 0o100646));
+        assert!(!matches("/022", 0o100644));
+        assert!(matches("/g+w,o+w", 0o100664));
+        assert!(matches("-u+s", 0o104755));
+        assert!(matches("/000", 0o100000));
+    }
+
+    #[test]
+    fn invalid() {
+        for spec in ["", "888", "77777", "q+w", "u+q", "u", "-g-w", "/", "u+w,"] {
+            assert_eq!(PermSpec::parse(spec), None, "{spec}");
+        }
+    }
+
+    #[test]
+    fn rendering() {
+        assert_eq!(render(0o100644), "-rw-r--r--");
+        assert_eq!(render(0o40755), "drwxr-xr-x");
+        assert_eq!(render(0o41777), "drwxrwxrwt");
+        assert_eq!(render(0o104755), "-rwsr-xr-x");
+        assert_eq!(render(0o102644), "-rw-r-Sr--");
+        assert_eq!(render(0o120777), "lrwxrwxrwx");
+    }
+}
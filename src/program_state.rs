@@ -1,9 +1,13 @@
+use crate::ast::Function;
 use crate::variables::*;
 use crate::RuntimeError;
 
 pub struct ProgramState<'a, 'b, T: crate::SyncWrite> {
     vars: VariableState<'b>,
 
+    /// The user-defined functions, indexed by `FunctionCall::id`.
+    functions: Vec<Function>,
+
     /// Where to write output to, typically stdout
     pub out: &'a T,
 
@@ -14,18 +18,28 @@ pub struct ProgramState<'a, 'b, T: crate::SyncWrite> {
 
 // XXX: use more descriptive lifetime names for this...
 impl<'a, T: crate::SyncWrite> ProgramState<'a, '_, T> {
-    pub fn new(num_scalars: usize, num_arrays: usize, out: &'a mut T) -> Self {
+    pub fn new(
+        num_scalars: usize,
+        num_arrays: usize,
+        functions: Vec<Function>,
+        out: &'a mut T,
+    ) -> Self {
         ProgramState {
             vars: VariableState::new(num_scalars, num_arrays),
+            functions,
             out,
             runtime_error: std::sync::OnceLock::new(),
         }
     }
 
-    pub fn vars(&self) -> &VariableState {
+    pub fn vars(&self) -> &VariableState<'_> {
         &self.vars
     }
 
+    pub fn function(&self, id: usize) -> &Function {
+        &self.functions[id]
+    }
+
     /// Returns true if `runtime_error` has been set, indicating that some worker experienced an
     /// error.
     pub fn check_runtime_error(&self) -> bool {
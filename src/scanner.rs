@@ -18,6 +18,20 @@ pub enum Token {
     Semicolon,
     Comma,
     Print,
+    Printf,
+    /// `!`, which is logical not
+    Bang,
+    /// The `not` keyword, which is logical not at a lower precedence than `!`
+    Not,
+    If,
+    Else,
+    While,
+    For,
+    In,
+    Break,
+    Continue,
+    Function,
+    Return,
     Value(Value),
     BinOp(OpKind),
     Attr(Attribute),
@@ -68,6 +82,15 @@ impl<'a> Scanner<'a> {
             '-' => self.oneplus_token('=', Token::MinusEqual, Token::BinOp(OpKind::Minus)),
             '*' => Token::BinOp(OpKind::Multiply),
             '/' => Token::BinOp(OpKind::Divide),
+            '%' => Token::BinOp(OpKind::Modulo),
+            '~' => Token::BinOp(OpKind::Match),
+            '&' => Token::BinOp(OpKind::BitAnd),
+            '|' => Token::BinOp(OpKind::BitOr),
+            '^' => Token::BinOp(OpKind::BitXor),
+            '!' => match self.oneplus_token('=', Token::BinOp(OpKind::NotEqual), Token::Bang) {
+                Token::Bang => self.oneplus_token('~', Token::BinOp(OpKind::NotMatch), Token::Bang),
+                t => t,
+            },
             '{' => Token::LeftBrace,
             '}' => Token::RightBrace,
             '[' => Token::LeftBracket,
@@ -81,6 +104,10 @@ impl<'a> Scanner<'a> {
                 self.current = ind;
                 self.string()
             }
+            '.' if matches!(self.chars.peek(), Some((_, '.'))) => {
+                self.chars.next();
+                Token::BinOp(OpKind::Concat)
+            }
             '.' => {
                 self.start = ind;
                 self.current = ind;
@@ -91,7 +118,7 @@ impl<'a> Scanner<'a> {
                 self.current = ind;
                 self.number()
             }
-            _ if ch.is_alphabetic() => {
+            _ if ch.is_alphabetic() || ch == '_' => {
                 self.start = ind;
                 self.current = ind;
                 self.word()
@@ -113,9 +140,9 @@ impl<'a> Scanner<'a> {
     fn string(&mut self) -> Token {
         loop {
             match self.chars.peek() {
-                Some((_, '"')) => {
+                Some(&(end, '"')) => {
                     self.chars.next();
-                    let s = &self.source[self.start..self.current + 1];
+                    let s = &self.source[self.start..end];
                     return Token::Value(Value::String(s.to_string()));
                 }
                 Some((ind, _)) => {
@@ -134,7 +161,7 @@ impl<'a> Scanner<'a> {
     fn attribute(&mut self) -> Token {
         loop {
             match self.chars.peek() {
-                Some((ind, ch)) if ch.is_alphanumeric() => {
+                Some((ind, ch)) if ch.is_alphanumeric() || *ch == '_' => {
                     self.current = *ind;
                     self.chars.next();
                 }
@@ -153,7 +180,7 @@ impl<'a> Scanner<'a> {
     fn word(&mut self) -> Token {
         loop {
             match self.chars.peek() {
-                Some((ind, ch)) if ch.is_alphanumeric() => {
+                Some((ind, ch)) if ch.is_alphanumeric() || *ch == '_' => {
                     self.current = *ind;
                     self.chars.next();
                 }
@@ -165,8 +192,21 @@ impl<'a> Scanner<'a> {
                         "END" => Token::End,
                         "end" => Token::End,
                         "print" => Token::Print,
+                        "printf" => Token::Printf,
+                        "if" => Token::If,
+                        "else" => Token::Else,
+                        "while" => Token::While,
+                        "for" => Token::For,
+                        "in" => Token::In,
+                        "break" => Token::Break,
+                        "continue" => Token::Continue,
+                        "function" => Token::Function,
+                        "return" => Token::Return,
                         "and" => Token::BinOp(OpKind::And),
                         "or" => Token::BinOp(OpKind::Or),
+                        "not" => Token::Not,
+                        "like" => Token::BinOp(OpKind::Like),
+                        "ilike" => Token::BinOp(OpKind::ILike),
                         a => self.identifier(a),
                     };
                 }
@@ -186,22 +226,71 @@ impl<'a> Scanner<'a> {
                     self.chars.next();
                 }
                 _ => {
-                    let num = match self.source[self.start..self.current + 1].parse::<i64>() {
+                    let text = self.current_token_text();
+                    if let Some(octal) = text.strip_prefix("0o") {
+                        return match i64::from_str_radix(octal, 8) {
+                            Ok(num) => Token::Value(Value::Int(num)),
+                            Err(e) => {
+                                self.error(&format!("Could not parse octal number '{text}': {e}"))
+                            }
+                        };
+                    }
+
+                    let split = text
+                        .find(|ch: char| !ch.is_ascii_digit())
+                        .unwrap_or(text.len());
+                    let (digits, suffix) = text.split_at(split);
+
+                    let num = match digits.parse::<i64>() {
                         Ok(num) => num,
                         Err(e) => {
-                            return self.error(&format!(
-                                "Could not parse number from '{}': {e}",
-                                self.current_token_text()
-                            ));
+                            return self
+                                .error(&format!("Could not parse number from '{text}': {e}"));
                         }
                     };
 
-                    return Token::Value(Value::Int(num));
+                    let Some(multiplier) = Self::suffix_multiplier(suffix) else {
+                        return self.error(&format!(
+                            "Unknown suffix '{suffix}' in number '{text}': expected a size \
+                             suffix (K, M, G, T, P, Ki, Mi, Gi, Ti, Pi) or a duration suffix \
+                             (s, m, h, d, w)"
+                        ));
+                    };
+
+                    return match num.checked_mul(multiplier) {
+                        Some(num) => Token::Value(Value::Int(num)),
+                        None => self.error(&format!("Number '{text}' is too large")),
+                    };
                 }
             }
         }
     }
 
+    /// Numbers can have a suffix giving their unit. Uppercase suffixes are sizes in bytes, either
+    /// powers of 1000 (`K`, `M`, ...) or powers of 1024 (`Ki`, `Mi`, ...). Lowercase suffixes are
+    /// durations in seconds, so that they can be compared against timestamps.
+    fn suffix_multiplier(suffix: &str) -> Option<i64> {
+        Some(match suffix {
+            "" => 1,
+            "K" => 1000,
+            "M" => 1000_i64.pow(2),
+            "G" => 1000_i64.pow(3),
+            "T" => 1000_i64.pow(4),
+            "P" => 1000_i64.pow(5),
+            "Ki" => 1 << 10,
+            "Mi" => 1 << 20,
+            "Gi" => 1 << 30,
+            "Ti" => 1 << 40,
+            "Pi" => 1 << 50,
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            _ => return None,
+        })
+    }
+
     fn current_token_text(&self) -> &'a str {
         &self.source[self.start..self.current + 1]
     }
@@ -229,6 +318,7 @@ impl<'a> Scanner<'a> {
 mod tests {
     use super::*;
 
+    #[allow(clippy::match_like_matches_macro)]
     fn is_error_token(t: Token) -> bool {
         match t {
             Token::Error(_) => true,
@@ -246,17 +336,75 @@ mod tests {
         assert_eq!(s.next_token(), Token::Eof);
     }
 
+    #[test]
+    fn octal_numbers() {
+        let mut s = Scanner::new("0o755 0o0 0o8 0o");
+
+        assert_eq!(s.next_token(), Token::Value(Value::Int(0o755)));
+        assert_eq!(s.next_token(), Token::Value(Value::Int(0)));
+        assert!(is_error_token(s.next_token()));
+        assert!(is_error_token(s.next_token()));
+        assert_eq!(s.next_token(), Token::Eof);
+    }
+
+    #[test]
+    fn number_suffixes() {
+        let mut s = Scanner::new("10K 1M 2G 1Ki 1Gi 30s 15m 7d 2w 1Mi 3h");
+
+        assert_eq!(s.next_token(), Token::Value(Value::Int(10_000)));
+        assert_eq!(s.next_token(), Token::Value(Value::Int(1_000_000)));
+        assert_eq!(s.next_token(), Token::Value(Value::Int(2_000_000_000)));
+        assert_eq!(s.next_token(), Token::Value(Value::Int(1024)));
+        assert_eq!(s.next_token(), Token::Value(Value::Int(1024 * 1024 * 1024)));
+        assert_eq!(s.next_token(), Token::Value(Value::Int(30)));
+        assert_eq!(s.next_token(), Token::Value(Value::Int(15 * 60)));
+        assert_eq!(s.next_token(), Token::Value(Value::Int(7 * 86400)));
+        assert_eq!(s.next_token(), Token::Value(Value::Int(2 * 7 * 86400)));
+        assert_eq!(s.next_token(), Token::Value(Value::Int(1024 * 1024)));
+        assert_eq!(s.next_token(), Token::Value(Value::Int(3 * 3600)));
+        assert_eq!(s.next_token(), Token::Eof);
+
+        let mut s = Scanner::new("10x 1KB 1mi 1k 9000000000000P");
+
+        assert_eq!(s.next_token(), Token::Error("Unknown suffix 'x' in number '10x': expected a size suffix (K, M, G, T, P, Ki, Mi, Gi, Ti, Pi) or a duration suffix (s, m, h, d, w)".to_string()));
+        assert!(is_error_token(s.next_token()));
+        assert!(is_error_token(s.next_token()));
+        assert!(is_error_token(s.next_token()));
+        assert_eq!(s.next_token(), Token::Error("Number '9000000000000P' is too large".to_string()));
+        assert_eq!(s.next_token(), Token::Eof);
+    }
+
     #[test]
     fn binary_operators() {
-        let mut s = Scanner::new("+ - */ > == =");
+        let mut s = Scanner::new("+ - */% > == = != ! .. ...size &|^");
 
         assert_eq!(s.next_token(), Token::BinOp(OpKind::Plus));
         assert_eq!(s.next_token(), Token::BinOp(OpKind::Minus));
         assert_eq!(s.next_token(), Token::BinOp(OpKind::Multiply));
         assert_eq!(s.next_token(), Token::BinOp(OpKind::Divide));
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::Modulo));
         assert_eq!(s.next_token(), Token::BinOp(OpKind::Greater));
         assert_eq!(s.next_token(), Token::BinOp(OpKind::EqualEqual));
         assert_eq!(s.next_token(), Token::Equal);
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::NotEqual));
+        assert_eq!(s.next_token(), Token::Bang);
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::Concat));
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::Concat));
+        assert_eq!(s.next_token(), Token::Attr(Attribute::Size));
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::BitAnd));
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::BitOr));
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::BitXor));
+        assert_eq!(s.next_token(), Token::Eof);
+    }
+
+    #[test]
+    fn match_operators() {
+        let mut s = Scanner::new("~ !~ ! ~");
+
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::Match));
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::NotMatch));
+        assert_eq!(s.next_token(), Token::Bang);
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::Match));
         assert_eq!(s.next_token(), Token::Eof);
     }
 
@@ -273,13 +421,26 @@ mod tests {
 
     #[test]
     fn keywords() {
-        let mut s = Scanner::new("BEGIN begin END end print");
+        let mut s = Scanner::new("BEGIN begin END end print printf not like ilike if else while for in break continue function return");
 
         assert_eq!(s.next_token(), Token::Begin);
         assert_eq!(s.next_token(), Token::Begin);
         assert_eq!(s.next_token(), Token::End);
         assert_eq!(s.next_token(), Token::End);
         assert_eq!(s.next_token(), Token::Print);
+        assert_eq!(s.next_token(), Token::Printf);
+        assert_eq!(s.next_token(), Token::Not);
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::Like));
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::ILike));
+        assert_eq!(s.next_token(), Token::If);
+        assert_eq!(s.next_token(), Token::Else);
+        assert_eq!(s.next_token(), Token::While);
+        assert_eq!(s.next_token(), Token::For);
+        assert_eq!(s.next_token(), Token::In);
+        assert_eq!(s.next_token(), Token::Break);
+        assert_eq!(s.next_token(), Token::Continue);
+        assert_eq!(s.next_token(), Token::Function);
+        assert_eq!(s.next_token(), Token::Return);
         assert_eq!(s.next_token(), Token::Eof);
     }
 
@@ -294,13 +455,15 @@ mod tests {
 
     #[test]
     fn identifiers() {
-        let mut s = Scanner::new("id id2 id .size id2");
+        let mut s = Scanner::new("id id2 id .size id2 _a b_c");
 
         assert_eq!(s.next_token(), Token::Identifier("id".to_string()));
         assert_eq!(s.next_token(), Token::Identifier("id2".to_string()));
         assert_eq!(s.next_token(), Token::Identifier("id".to_string()));
         assert_eq!(s.next_token(), Token::Attr(Attribute::Size));
         assert_eq!(s.next_token(), Token::Identifier("id2".to_string()));
+        assert_eq!(s.next_token(), Token::Identifier("_a".to_string()));
+        assert_eq!(s.next_token(), Token::Identifier("b_c".to_string()));
         assert_eq!(s.next_token(), Token::Eof);
     }
 
@@ -314,6 +477,20 @@ mod tests {
         assert_eq!(s.next_token(), Token::Eof);
     }
 
+    #[test]
+    fn multibyte_strings() {
+        // A string ending in a multibyte character used to be sliced in the middle of it:
+        let mut s = Scanner::new("\"né\" \"日本\" \"ü\"..\"\" \"\"");
+
+        assert_eq!(s.next_token(), Token::Value(Value::String("né".to_string())));
+        assert_eq!(s.next_token(), Token::Value(Value::String("日本".to_string())));
+        assert_eq!(s.next_token(), Token::Value(Value::String("ü".to_string())));
+        assert_eq!(s.next_token(), Token::BinOp(OpKind::Concat));
+        assert_eq!(s.next_token(), Token::Value(Value::String("".to_string())));
+        assert_eq!(s.next_token(), Token::Value(Value::String("".to_string())));
+        assert_eq!(s.next_token(), Token::Eof);
+    }
+
     #[test]
     fn other_tokens() {
         let mut s = Scanner::new("{ } ;, []");
@@ -0,0 +1,93 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Local, LocalResult, NaiveDate, NaiveDateTime, TimeZone};
+
+use crate::RuntimeError;
+
+/// How times are displayed when printed.
+const DISPLAY_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// The current time, in nanoseconds since the epoch.
+pub fn now() -> i64 {
+    Local::now().timestamp_nanos_opt().unwrap_or(i64::MAX)
+}
+
+/// Formats the time `t`, in nanoseconds since the epoch, in the local timezone according to the
+/// strftime-style format `fmt`.
+pub fn strftime(fmt: &str, t: i64) -> Result<String, RuntimeError> {
+    let t = local(t);
+
+    let mut s = String::new();
+    match write!(s, "{}", t.format(fmt)) {
+        Ok(()) => Ok(s),
+        Err(_) => Err(RuntimeError::new(&format!(
+            "Invalid time format string \"{fmt}\""
+        ))),
+    }
+}
+
+/// Formats the time `t`, in nanoseconds since the epoch, for printing.
+pub fn display(t: i64) -> String {
+    local(t).format(DISPLAY_FORMAT).to_string()
+}
+
+/// Parses a date, optionally followed by a time, in the local timezone, returning the number of
+/// nanoseconds since the epoch. Accepts `2024-01-31`, `2024-01-31 13:45`, and `2024-01-31 13:45:10`,
+/// with either a space or a `T` between the date and the time.
+pub fn parse_date(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let datetime = match NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        Ok(date) => date.and_hms_opt(0, 0, 0)?,
+        Err(_) => [
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y-%m-%d %H:%M",
+            "%Y-%m-%dT%H:%M",
+        ]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())?,
+    };
+
+    // A time that is skipped by a DST change doesn't exist, and one that is repeated is
+    // ambiguous, in which case the earlier one is used:
+    match Local.from_local_datetime(&datetime) {
+        LocalResult::Single(t) => t.timestamp_nanos_opt(),
+        LocalResult::Ambiguous(t, _) => t.timestamp_nanos_opt(),
+        LocalResult::None => None,
+    }
+}
+
+fn local(t: i64) -> DateTime<Local> {
+    DateTime::from_timestamp_nanos(t).with_timezone(&Local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let t = parse_date("2024-01-31").unwrap();
+        assert_eq!(display(t), "2024-01-31 00:00:00");
+        assert_eq!(strftime("%Y/%m/%d %H:%M", t).unwrap(), "2024/01/31 00:00");
+
+        let t = parse_date("2024-01-31 13:45").unwrap();
+        assert_eq!(display(t), "2024-01-31 13:45:00");
+        assert_eq!(parse_date("2024-01-31T13:45:00"), Some(t));
+        assert_eq!(parse_date("2024-01-31 13:45:10"), Some(t + 10_000_000_000));
+        assert_eq!(
+            strftime("%H:%M:%S.%f", t + 1_500_000_000).unwrap(),
+            "13:45:01.500000000"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(parse_date(""), None);
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("2024-01-31 25:00"), None);
+        assert_eq!(parse_date("yesterday"), None);
+        assert_eq!(parse_date("3000-01-01"), None);
+        assert!(strftime("%Q", 0).is_err());
+    }
+}
@@ -1,10 +1,93 @@
 use crossbeam::deque::{Steal, Stealer, Worker};
-use std::path::{Path, PathBuf};
+use std::fs::DirEntry;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-use crate::ast::{run_routines, FileState, Routine};
+use crate::ast::{run_routines, FileState, Routine, STACK_SIZE};
 use crate::program_state::ProgramState;
 use crate::Args;
 
+/// A directory that is waiting to be read.
+struct Dir {
+    path: PathBuf,
+    /// How many directories below the root of the walk the directory is.
+    depth: usize,
+    /// When following symlinks, the chain of directories from this one up to the root, used to
+    /// detect symlinks that point back to a directory being walked.
+    ancestors: Option<Arc<Ancestor>>,
+}
+
+/// A directory identified by its device and inode numbers, linked to its parent.
+struct Ancestor {
+    dev: u64,
+    ino: u64,
+    parent: Option<Arc<Ancestor>>,
+}
+
+impl Dir {
+    fn root(f: FileState, follow: bool) -> Self {
+        let ancestors = match follow {
+            true => f.get_metadata().as_ref().ok().map(|md| {
+                Arc::new(Ancestor {
+                    dev: md.dev(),
+                    ino: md.ino(),
+                    parent: None,
+                })
+            }),
+            false => None,
+        };
+
+        Dir {
+            path: f.path,
+            depth: f.depth,
+            ancestors,
+        }
+    }
+
+    /// If the entry `ent` of this directory, whose FileState is `f`, is a directory that should
+    /// be walked, returns it.
+    ///
+    /// Without following symlinks, that is any directory. When following them, a symlink to a
+    /// directory counts too, but not if the directory is one of its own ancestors, since walking
+    /// it would never end.
+    fn subdir(&self, ent: &DirEntry, f: &FileState) -> Option<Dir> {
+        let Some(ancestors) = &self.ancestors else {
+            return match ent.file_type() {
+                Ok(ty) if ty.is_dir() => Some(Dir {
+                    path: ent.path(),
+                    depth: self.depth + 1,
+                    ancestors: None,
+                }),
+                _ => None,
+            };
+        };
+
+        let md = f.get_metadata().as_ref().ok()?;
+        if !md.is_dir() {
+            return None;
+        }
+
+        let mut ancestor = Some(ancestors);
+        while let Some(a) = ancestor {
+            if (a.dev, a.ino) == (md.dev(), md.ino()) {
+                eprintln!("Filesystem loop detected: {}", f.path.display());
+                return None;
+            }
+            ancestor = a.parent.as_ref();
+        }
+
+        Some(Dir {
+            path: ent.path(),
+            depth: self.depth + 1,
+            ancestors: Some(Arc::new(Ancestor {
+                dev: md.dev(),
+                ino: md.ino(),
+                parent: Some(ancestors.clone()),
+            })),
+        })
+    }
+}
+
 pub fn treewalk<T: crate::SyncWrite>(
     args: &Args,
     routines: &Vec<Routine>,
@@ -18,22 +101,23 @@ pub fn treewalk<T: crate::SyncWrite>(
     run_routines(routines, &f, p)?;
 
     match args.n_threads {
-        1 => treewalk_single_threaded(routines, f, p),
+        1 => treewalk_single_threaded(args, routines, f, p),
         _ => treewalk_multi_threaded(args, routines, f, p),
     }
 }
 
 fn treewalk_single_threaded<T: crate::SyncWrite>(
+    args: &Args,
     routines: &[Routine],
     f: FileState,
     p: &ProgramState<'_, '_, T>,
 ) -> Result<(), crate::RuntimeError> {
-    let mut stack: Vec<std::path::PathBuf> = Vec::new();
-    stack.push(f.path);
+    let mut stack: Vec<Dir> = Vec::new();
+    stack.push(Dir::root(f, args.follow));
 
-    while let Some(path) = stack.pop() {
+    while let Some(dir) = stack.pop() {
         // XXX: flatten() instead of unwrap()?
-        for ent in std::fs::read_dir(path).unwrap() {
+        for ent in std::fs::read_dir(&dir.path).unwrap() {
             let Ok(ent) = ent else {
                 continue;
             };
@@ -44,15 +128,12 @@ fn treewalk_single_threaded<T: crate::SyncWrite>(
                 _ => {}
             };
 
-            let Ok(ty) = ent.file_type() else {
-                continue;
-            };
+            let f = FileState::new(ent.path(), dir.depth + 1, None, args);
 
-            if ty.is_dir() {
-                stack.push(ent.path());
+            if let Some(subdir) = dir.subdir(&ent, &f) {
+                stack.push(subdir);
             }
 
-            let f = FileState::new(ent.path(), None);
             run_routines(routines, &f, p)?;
         }
     }
@@ -62,7 +143,9 @@ fn treewalk_single_threaded<T: crate::SyncWrite>(
 
 struct State<'a, 'p1, 'p2, T: crate::SyncWrite> {
     n_workers: usize,
-    stealers: &'a [Stealer<PathBuf>],
+    args: &'a Args,
+    /// Directories to process.
+    stealers: &'a [Stealer<Dir>],
     routines: &'p1 Vec<Routine>,
     prog_state: &'p1 ProgramState<'p1, 'p2, T>,
 }
@@ -73,8 +156,8 @@ fn treewalk_multi_threaded<'p1, T: crate::SyncWrite>(
     f: FileState,
     p: &'p1 ProgramState<'p1, '_, T>,
 ) -> Result<(), crate::RuntimeError> {
-    let mut workers: Vec<Worker<PathBuf>> = Vec::new();
-    let mut stealers: Vec<Stealer<PathBuf>> = Vec::new();
+    let mut workers: Vec<Worker<Dir>> = Vec::new();
+    let mut stealers: Vec<Stealer<Dir>> = Vec::new();
 
     for _ in 0..args.n_threads {
         let worker = Worker::new_fifo();
@@ -84,19 +167,23 @@ fn treewalk_multi_threaded<'p1, T: crate::SyncWrite>(
 
     let state = State {
         n_workers: args.n_threads,
+        args,
         stealers: &stealers,
         routines,
         prog_state: p,
     };
 
-    workers[0].push(f.path);
+    workers[0].push(Dir::root(f, args.follow));
 
     std::thread::scope(|s| {
         (0..args.n_threads)
             .map(|_| {
                 let worker = workers.pop().unwrap();
                 let state = &state;
-                s.spawn(move || worker_main(&worker, state))
+                std::thread::Builder::new()
+                    .stack_size(STACK_SIZE)
+                    .spawn_scoped(s, move || worker_main(&worker, state))
+                    .unwrap()
             })
             .map(|t| t.join().unwrap())
             // If any of the threads had an error, return the first error, otherwise, Ok():
@@ -106,7 +193,7 @@ fn treewalk_multi_threaded<'p1, T: crate::SyncWrite>(
 }
 
 fn worker_main<T: crate::SyncWrite>(
-    w: &Worker<PathBuf>,
+    w: &Worker<Dir>,
     state: &State<T>,
 ) -> Result<(), crate::RuntimeError> {
     loop {
@@ -115,7 +202,7 @@ fn worker_main<T: crate::SyncWrite>(
         };
 
         match find_task(w, state) {
-            Some(path) => process_directory(&path, w, state)
+            Some(dir) => process_directory(&dir, w, state)
                 .inspect_err(|e| state.prog_state.set_runtime_error(e.clone()))?,
             // TODO: proper termination detecton.
             None => break,
@@ -125,7 +212,7 @@ fn worker_main<T: crate::SyncWrite>(
     Ok(())
 }
 
-fn find_task<T: crate::SyncWrite>(local: &Worker<PathBuf>, state: &State<T>) -> Option<PathBuf> {
+fn find_task<T: crate::SyncWrite>(local: &Worker<Dir>, state: &State<T>) -> Option<Dir> {
     if let Some(task) = local.pop() {
         return Some(task);
     }
@@ -140,15 +227,15 @@ fn find_task<T: crate::SyncWrite>(local: &Worker<PathBuf>, state: &State<T>) ->
 }
 
 fn process_directory<T: crate::SyncWrite>(
-    path: &Path,
-    w: &Worker<PathBuf>,
+    dir: &Dir,
+    w: &Worker<Dir>,
     state: &State<T>,
 ) -> Result<(), crate::RuntimeError> {
-    let Ok(dir) = std::fs::read_dir(path) else {
+    let Ok(entries) = std::fs::read_dir(&dir.path) else {
         return Ok(());
     };
 
-    for ent in dir {
+    for ent in entries {
         let Ok(ent) = ent else {
             continue;
         };
@@ -159,16 +246,12 @@ fn process_directory<T: crate::SyncWrite>(
             _ => {}
         };
 
-        let f = FileState::new(ent.path(), None);
+        let f = FileState::new(ent.path(), dir.depth + 1, None, state.args);
 
         run_routines(state.routines, &f, state.prog_state)?;
 
-        let Ok(ty) = ent.file_type() else {
-            continue;
-        };
-
-        if ty.is_dir() {
-            w.push(ent.path());
+        if let Some(subdir) = dir.subdir(&ent, &f) {
+            w.push(subdir);
         }
     }
 
@@ -1,7 +1,6 @@
-use std::os::unix::fs::FileTypeExt;
-use std::os::unix::fs::MetadataExt;
-
+use crate::access::Access;
 use crate::ast::*;
+use crate::metadata::Metadata;
 use crate::RuntimeError;
 
 #[derive(Eq, Hash, Clone, PartialEq, Debug)]
@@ -14,6 +13,8 @@ pub enum Value {
     Special(SpecialValue),
 }
 
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+
 /// This is for types that are represented as integers, but are distinct types that should not
 /// be treated as integers semantically because arithmetic operations do not make sense for them.
 #[derive(Eq, Hash, Clone, PartialEq, Debug)]
@@ -26,9 +27,14 @@ pub struct SpecialValue {
 pub enum SpecialValueKind {
     Ino,
     Mode,
-    /// Both UIDs and GIDs
+    /// UIDs and GIDs can also be compared to user and group names
     Uid,
+    Gid,
+    /// Device numbers, which are displayed as "major:minor"
     Devno,
+    /// A point in time, with nanosecond precision. Unlike other special values, times can be
+    /// ordered, and can have durations added to or subtracted from them.
+    Time,
 }
 
 impl Value {
@@ -69,34 +75,145 @@ impl Value {
         })
     }
 
+    pub fn unary_op(self, op: UnaryKind) -> Result<Value, RuntimeError> {
+        Ok(match op {
+            UnaryKind::Not => Value::Boolean(!self.is_truthy()?),
+            UnaryKind::Negate => match self {
+                Value::Special(s) => {
+                    return Err(RuntimeError::new(&format!(
+                        "Cannot negate a special value '{:?}'",
+                        s
+                    )))
+                }
+                v => match v.to_signed_int()?.checked_neg() {
+                    Some(i) => Value::Int(i),
+                    None => return Err(RuntimeError::new("Integer overflow in negation")),
+                },
+            },
+        })
+    }
+
     pub fn binary_op(self, other: Value, op: OpKind) -> Result<Value, RuntimeError> {
+        if let Some(res) = SpecialValue::time_op(&self, &other, op) {
+            return res;
+        }
+
         Ok(match op {
-            OpKind::Plus => Self::integer_op(self, other, |l, r| l + r)?,
-            OpKind::Minus => Self::integer_op(self, other, |l, r| l - r)?,
-            OpKind::Multiply => Self::integer_op(self, other, |l, r| l * r)?,
-            OpKind::Divide => Self::integer_op(self, other, |l, r| l / r)?,
-            OpKind::Greater => Self::int_to_bool_op(self, other, |l, r| l > r)?,
-            OpKind::GreaterEqual => Self::int_to_bool_op(self, other, |l, r| l >= r)?,
-            OpKind::Less => Self::int_to_bool_op(self, other, |l, r| l < r)?,
-            OpKind::LessEqual => Self::int_to_bool_op(self, other, |l, r| l <= r)?,
+            OpKind::Plus => Self::integer_op(self, other, i64::checked_add)?,
+            OpKind::Minus => Self::integer_op(self, other, i64::checked_sub)?,
+            OpKind::Multiply => Self::integer_op(self, other, i64::checked_mul)?,
+            OpKind::Divide => Self::division(self, other, i64::checked_div)?,
+            OpKind::Modulo => Self::division(self, other, i64::checked_rem)?,
+            OpKind::Concat => Value::String(format!("{self}{other}")),
+            OpKind::BitAnd => Self::bitwise_op(self, other, |l, r| l & r)?,
+            OpKind::BitOr => Self::bitwise_op(self, other, |l, r| l | r)?,
+            OpKind::BitXor => Self::bitwise_op(self, other, |l, r| l ^ r)?,
+            OpKind::Greater => Self::ordering(self, other, |o| o.is_gt())?,
+            OpKind::GreaterEqual => Self::ordering(self, other, |o| o.is_ge())?,
+            OpKind::Less => Self::ordering(self, other, |o| o.is_lt())?,
+            OpKind::LessEqual => Self::ordering(self, other, |o| o.is_le())?,
             OpKind::EqualEqual => Self::equality(self, other)?,
+            OpKind::NotEqual => Value::Boolean(!Self::equality(self, other)?.is_truthy()?),
+            OpKind::Match | OpKind::NotMatch | OpKind::Like | OpKind::ILike => {
+                Self::pattern_match(self, other, op)?
+            }
             OpKind::And => Value::Boolean(self.is_truthy()? && other.is_truthy()?),
             OpKind::Or => Value::Boolean(self.is_truthy()? || other.is_truthy()?),
         })
     }
 
-    fn int_to_bool_op(l: Value, r: Value, f: fn(i64, i64) -> bool) -> Result<Value, RuntimeError> {
+    /// Implements the ordering operators `<`, `<=`, `>`, and `>=`:
+    ///
+    /// - If both operands are strings, they are compared lexicographically, byte by byte, so
+    ///   `"10" < "9"` and `"Z" < "a"`.
+    /// - Otherwise, both operands are converted to integers, as for arithmetic. A string that
+    ///   isn't a number is 0, and booleans are 0 or 1, so `.name > 0` is false for any name that
+    ///   isn't a positive number.
+    /// - Special values such as inode numbers have no meaningful order, so ordering them is a
+    ///   runtime error.
+    fn ordering(
+        l: Value,
+        r: Value,
+        f: fn(std::cmp::Ordering) -> bool,
+    ) -> Result<Value, RuntimeError> {
+        let ord = match (l, r) {
+            (Value::String(l), Value::String(r)) => l.cmp(&r),
+            (l, r) => l.to_signed_int()?.cmp(&r.to_signed_int()?),
+        };
+        Ok(Value::Boolean(f(ord)))
+    }
+
+    /// Division and modulo, which are runtime errors rather than panics when dividing by zero.
+    fn division(l: Value, r: Value, f: fn(i64, i64) -> Option<i64>) -> Result<Value, RuntimeError> {
         let l = l.to_signed_int()?;
         let r = r.to_signed_int()?;
-        Ok(Value::Boolean(f(l, r)))
+        if r == 0 {
+            return Err(RuntimeError::new("Division by zero"));
+        }
+        Self::checked_result(f(l, r))
     }
 
-    fn integer_op(l: Value, r: Value, f: fn(i64, i64) -> i64) -> Result<Value, RuntimeError> {
+    /// Applies the checked arithmetic operation `f`, which returns None if the result overflows.
+    fn integer_op(
+        l: Value,
+        r: Value,
+        f: fn(i64, i64) -> Option<i64>,
+    ) -> Result<Value, RuntimeError> {
         let l = l.to_signed_int()?;
         let r = r.to_signed_int()?;
-        Ok(Value::Int(f(l, r)))
+        Self::checked_result(f(l, r))
+    }
+
+    fn checked_result(i: Option<i64>) -> Result<Value, RuntimeError> {
+        match i {
+            Some(i) => Ok(Value::Int(i)),
+            None => Err(RuntimeError::new("Integer overflow in arithmetic")),
+        }
+    }
+
+    /// Bitwise operators work on integers and, unlike other arithmetic, on modes, so that
+    /// `.mode & 0o022` gives the group and world write bits. The result is an integer.
+    fn bitwise_op(l: Value, r: Value, f: fn(i64, i64) -> i64) -> Result<Value, RuntimeError> {
+        Ok(Value::Int(f(l.into_bits()?, r.into_bits()?)))
+    }
+
+    fn into_bits(self) -> Result<i64, RuntimeError> {
+        match self {
+            Value::Special(s) if s.kind == SpecialValueKind::Mode => Ok(s.val as i64),
+            v => v.to_signed_int(),
+        }
+    }
+
+    /// Interprets the value as a file mode: either a mode, such as `.mode`, or an integer such as
+    /// `0o644`.
+    pub fn to_mode(self) -> Result<u32, RuntimeError> {
+        let bits = self.into_bits()?;
+        bits.try_into()
+            .map_err(|_| RuntimeError::new(&format!("Invalid file mode {bits}")))
+    }
+
+    /// Matches `l` against `pattern`, which is a regex or glob depending on `op`. This is only
+    /// used when the pattern isn't known until runtime; literal patterns are compiled ahead of
+    /// time into a `RegexMatch`.
+    fn pattern_match(l: Value, pattern: Value, op: OpKind) -> Result<Value, RuntimeError> {
+        let re = match compile_pattern(&pattern.to_string(), op) {
+            Ok(re) => re,
+            Err(e) => {
+                return Err(RuntimeError::new(&format!(
+                    "Invalid regular expression: {e}"
+                )))
+            }
+        };
+
+        Ok(Value::Boolean(
+            re.is_match(&l.to_string()) != (op == OpKind::NotMatch),
+        ))
     }
 
+    /// Implements `==` and `!=`. Unlike the ordering operators, values are not converted, so
+    /// values of different types are never equal: `"1" == 1` and `1 == True` are both false.
+    /// Special values are the exception, and can be compared to non-negative integers, or in the
+    /// case of `.owner` and `.group`, to user and group names.
     fn equality(val1: Value, val2: Value) -> Result<Value, RuntimeError> {
         if let Value::Special(s) = val1 {
             return s.binary_op(OpKind::EqualEqual, val2);
@@ -114,6 +231,18 @@ impl Value {
     }
 }
 
+/// Compiles the right-hand side of a pattern matching operator into a regex: `~` and `!~` take a
+/// regex as-is, while `like` and `ilike` take a shell glob.
+pub fn compile_pattern(pattern: &str, op: OpKind) -> Result<regex::Regex, regex::Error> {
+    match op {
+        OpKind::Like => regex::Regex::new(&crate::glob::to_regex(pattern)),
+        OpKind::ILike => regex::RegexBuilder::new(&crate::glob::to_regex(pattern))
+            .case_insensitive(true)
+            .build(),
+        _ => regex::Regex::new(pattern),
+    }
+}
+
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -129,10 +258,131 @@ impl std::fmt::Display for Value {
 }
 
 impl SpecialValue {
+    #[allow(clippy::new_ret_no_self)]
     fn new(val: u64, kind: SpecialValueKind) -> Value {
         Value::Special(SpecialValue { val, kind })
     }
 
+    /// The underlying number, for when it needs to be displayed in some other way. For times, this
+    /// is whole seconds since the epoch.
+    pub fn raw(&self) -> i128 {
+        match self.kind {
+            SpecialValueKind::Time => self.time_ns().div_euclid(NANOS_PER_SEC).into(),
+            _ => self.val.into(),
+        }
+    }
+
+    /// The user ID `uid`.
+    pub fn new_uid(uid: u32) -> Value {
+        Self::new(uid.into(), SpecialValueKind::Uid)
+    }
+
+    /// The group ID `gid`.
+    pub fn new_gid(gid: u32) -> Value {
+        Self::new(gid.into(), SpecialValueKind::Gid)
+    }
+
+    /// A time `secs` seconds plus `nsecs` nanoseconds after the epoch. Times too far from the
+    /// epoch to represent in nanoseconds, which are more than 292 years away, are clamped.
+    pub fn new_time(secs: i64, nsecs: i64) -> Value {
+        Self::new(nanoseconds(secs, nsecs) as u64, SpecialValueKind::Time)
+    }
+
+    /// The time, in nanoseconds since the epoch, if this is a time.
+    pub fn time(&self) -> Result<i64, RuntimeError> {
+        match self.kind {
+            SpecialValueKind::Time => Ok(self.time_ns()),
+            _ => Err(RuntimeError::new(&format!(
+                "Cannot evaluate a special value '{:?}' as a time",
+                self
+            ))),
+        }
+    }
+
+    /// The device number, if this is one.
+    pub fn devno(&self) -> Result<u64, RuntimeError> {
+        match self.kind {
+            SpecialValueKind::Devno => Ok(self.val),
+            _ => Err(RuntimeError::new(&format!(
+                "Cannot evaluate a special value '{:?}' as a device number",
+                self
+            ))),
+        }
+    }
+
+    /// Times are stored as nanoseconds since the epoch, as the bits of a signed number since they
+    /// can be before the epoch.
+    fn time_ns(&self) -> i64 {
+        self.val as i64
+    }
+
+    /// If `l` or `r` is a time, try to apply `op` to them:
+    ///
+    /// - Subtracting two times gives the number of whole seconds between them, as an integer.
+    /// - Adding or subtracting an integer number of seconds to a time gives a time.
+    /// - Times can be compared with times, exactly: they have nanosecond precision, so a file
+    ///   modified half a second after another has a greater `.mtime`.
+    /// - Times can also be compared with integers, which are seconds since the epoch. Only the
+    ///   whole seconds of the time are compared, so `.mtime == 1700000000` for a file modified at
+    ///   any point during that second.
+    ///
+    /// Returns None if neither operand is a time, or if `op` isn't one of these, so that the
+    /// usual rules for special values apply.
+    fn time_op(l: &Value, r: &Value, op: OpKind) -> Option<Result<Value, RuntimeError>> {
+        let time = |v: &Value| match v {
+            Value::Special(s) if s.kind == SpecialValueKind::Time => Some(s.time_ns()),
+            _ => None,
+        };
+        let int = |v: &Value| match v {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        };
+        // Integers are in seconds, so they need converting to nanoseconds to add to a time:
+        let ns = |i: i64| i.checked_mul(NANOS_PER_SEC);
+
+        let overflow = || RuntimeError::new("Integer overflow in time arithmetic");
+        let new_time =
+            |ns: Option<i64>| Some(ns.map(|ns| Self::new_time(0, ns)).ok_or_else(overflow));
+        let compare = |l: i64, r: i64| {
+            Some(Ok(Value::Boolean(match op {
+                OpKind::EqualEqual => l == r,
+                OpKind::NotEqual => l != r,
+                OpKind::Greater => l > r,
+                OpKind::GreaterEqual => l >= r,
+                OpKind::Less => l < r,
+                OpKind::LessEqual => l <= r,
+                _ => return None,
+            })))
+        };
+
+        match (time(l), time(r)) {
+            (Some(l), Some(r)) => match op {
+                OpKind::Minus => Some(
+                    l.checked_sub(r)
+                        .map(|ns| Value::Int(ns.div_euclid(NANOS_PER_SEC)))
+                        .ok_or_else(overflow),
+                ),
+                _ => compare(l, r),
+            },
+            (Some(t), None) => {
+                let i = int(r)?;
+                match op {
+                    OpKind::Plus => new_time(ns(i).and_then(|i| t.checked_add(i))),
+                    OpKind::Minus => new_time(ns(i).and_then(|i| t.checked_sub(i))),
+                    _ => compare(t.div_euclid(NANOS_PER_SEC), i),
+                }
+            }
+            (None, Some(t)) => {
+                let i = int(l)?;
+                match op {
+                    OpKind::Plus => new_time(ns(i).and_then(|i| t.checked_add(i))),
+                    _ => compare(i, t.div_euclid(NANOS_PER_SEC)),
+                }
+            }
+            (None, None) => None,
+        }
+    }
+
     fn binary_op(self, op: OpKind, other: Value) -> Result<Value, RuntimeError> {
         match op {
             OpKind::EqualEqual => Ok(self.equality(other)?),
@@ -163,10 +413,22 @@ impl SpecialValue {
                     Value::Boolean(false)
                 })
             }
+            // UIDs and GIDs can be compared with names, which are equal if they name the same ID.
+            // A name that doesn't exist isn't equal to anything:
+            Value::String(ref name)
+                if matches!(self.kind, SpecialValueKind::Uid | SpecialValueKind::Gid) =>
+            {
+                let id = match self.kind {
+                    SpecialValueKind::Uid => crate::users::uid(name),
+                    _ => crate::users::gid(name),
+                };
+                Ok(Value::Boolean(id.map(u64::from) == Some(self.val)))
+            }
             // Special values can be compared for equality with other special values of the
-            // same type only:
+            // same type only, except that UIDs and GIDs can be compared with each other:
             Value::Special(ref s) => {
-                if self.kind == s.kind {
+                let ids = [SpecialValueKind::Uid, SpecialValueKind::Gid];
+                if self.kind == s.kind || (ids.contains(&self.kind) && ids.contains(&s.kind)) {
                     Ok(if self.val == s.val {
                         Value::Boolean(true)
                     } else {
@@ -191,6 +453,15 @@ impl std::fmt::Display for SpecialValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.kind {
             SpecialValueKind::Mode => write!(f, "{:#o}", self.val),
+            SpecialValueKind::Devno => {
+                write!(
+                    f,
+                    "{}:{}",
+                    crate::metadata::major(self.val),
+                    crate::metadata::minor(self.val)
+                )
+            }
+            SpecialValueKind::Time => write!(f, "{}", crate::time::display(self.time_ns())),
             _ => write!(f, "{}", self.val),
         }
     }
@@ -206,24 +477,82 @@ pub enum Attribute {
     /// Inode number
     Ino,
     Mode,
+    /// Permissions as shown by `ls -l`, such as "-rw-r--r--"
+    Perms,
     /// Filename
     Name,
     NLink,
     Owner,
     Group,
+    /// Names of the owner and group, or their IDs if they have no names
+    OwnerName,
+    GroupName,
     /// Full path
     Path,
+    /// Extension of the filename, without the leading '.'
+    Ext,
+    /// Filename without its extension
+    Stem,
+    /// Path of the directory containing the file
+    Parent,
+    /// Path relative to the root of the walk
+    RelPath,
+    /// Number of directories below the root of the walk
+    Depth,
     /// Device number of the file itself (special files)
     RDev,
     Size,
     Atime,
     Mtime,
     Ctime,
-    // XXX: include birthtime?
+    /// Timestamps as integer nanoseconds since the epoch
+    AtimeNs,
+    MtimeNs,
+    CtimeNs,
+    /// Seconds since the file was last accessed, modified, or changed. These are derived from the
+    /// time the program started, so the compiler turns them into a subtraction from `now`; see
+    /// `Attribute::age_of()`.
+    AAge,
+    MAge,
+    CAge,
+    /// Birth time, when the file was created
+    Btime,
+    /// File attributes such as "immutable" and "append", as a comma-separated list. Attributes the
+    /// filesystem doesn't support are never listed.
+    Attributes,
+    /// ID of the mount containing the file
+    MntId,
+    /// Names of the fields that statx() reported for the file, such as "btime", as a
+    /// comma-separated list. Asking for a field that isn't there is an error.
+    StatxMask,
+    /// Type of the filesystem the file is on, such as "ext4"
+    FsType,
+    /// Whether the file is the root of a mount
+    Mountpoint,
+    /// Names of the file's extended attributes, as a comma-separated list
+    Xattrs,
+    /// What a symlink points to, or "" if the file isn't a symlink
+    Target,
+    /// Whether the file is a symlink that points to a file that doesn't exist
+    Broken,
+    /// Number of lines in the file, or 0 if it isn't a regular file
+    Lines,
+    /// Whether a regular file's first few KB look like text or binary data
+    IsText,
+    IsBinary,
+    /// Whether the user running the program can read, write, or execute the file
+    Readable,
+    Writable,
+    Executable,
+    /// What kind of file it is judging by its contents, such as "image/png" or "PNG image", or ""
+    /// if it isn't a regular file
+    Mime,
+    Magic,
     Type,
 }
 
 impl Attribute {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(a: &str) -> Option<Self> {
         Some(match a {
             ".blksize" => Attribute::BlkSize,
@@ -231,21 +560,62 @@ impl Attribute {
             ".dev" => Attribute::Dev,
             ".ino" => Attribute::Ino,
             ".mode" => Attribute::Mode,
+            ".perms" => Attribute::Perms,
             ".name" => Attribute::Name,
             ".nlink" => Attribute::NLink,
             ".owner" => Attribute::Owner,
             ".group" => Attribute::Group,
+            ".owner_name" => Attribute::OwnerName,
+            ".group_name" => Attribute::GroupName,
             ".path" => Attribute::Path,
+            ".ext" => Attribute::Ext,
+            ".stem" => Attribute::Stem,
+            ".parent" => Attribute::Parent,
+            ".relpath" => Attribute::RelPath,
+            ".depth" => Attribute::Depth,
             ".rdev" => Attribute::RDev,
             ".size" => Attribute::Size,
             ".atime" => Attribute::Atime,
             ".mtime" => Attribute::Mtime,
             ".ctime" => Attribute::Ctime,
+            ".atime_ns" => Attribute::AtimeNs,
+            ".mtime_ns" => Attribute::MtimeNs,
+            ".ctime_ns" => Attribute::CtimeNs,
+            ".aage" => Attribute::AAge,
+            ".age" | ".mage" => Attribute::MAge,
+            ".cage" => Attribute::CAge,
+            ".btime" => Attribute::Btime,
+            ".attributes" => Attribute::Attributes,
+            ".mnt_id" => Attribute::MntId,
+            ".statx_mask" => Attribute::StatxMask,
+            ".xattrs" => Attribute::Xattrs,
+            ".fstype" => Attribute::FsType,
+            ".mountpoint" => Attribute::Mountpoint,
+            ".target" => Attribute::Target,
+            ".broken" => Attribute::Broken,
+            ".lines" => Attribute::Lines,
+            ".is_text" => Attribute::IsText,
+            ".is_binary" => Attribute::IsBinary,
+            ".readable" => Attribute::Readable,
+            ".writable" => Attribute::Writable,
+            ".executable" => Attribute::Executable,
+            ".mime" => Attribute::Mime,
+            ".magic" => Attribute::Magic,
             ".type" => Attribute::Type,
             _ => return None,
         })
     }
 
+    /// For an attribute that is the age of one of a file's timestamps, the timestamp's attribute.
+    pub fn age_of(&self) -> Option<Attribute> {
+        match self {
+            Attribute::AAge => Some(Attribute::Atime),
+            Attribute::MAge => Some(Attribute::Mtime),
+            Attribute::CAge => Some(Attribute::Ctime),
+            _ => None,
+        }
+    }
+
     pub fn evaluate(&self, f: Option<&FileState>) -> crate::Result<Value> {
         match f {
             Some(f) => self.evaluate_with_file(f),
@@ -260,6 +630,53 @@ impl Attribute {
                 None => f.path.display().to_string(),
             }),
             Attribute::Path => Value::String(f.path.display().to_string()),
+            Attribute::Ext => Value::String(match f.path.extension() {
+                Some(s) => s.to_string_lossy().to_string(),
+                None => String::new(),
+            }),
+            Attribute::Stem => Value::String(match f.path.file_stem() {
+                Some(s) => s.to_string_lossy().to_string(),
+                None => String::new(),
+            }),
+            Attribute::Parent => Value::String(match f.path.parent() {
+                Some(p) => p.display().to_string(),
+                None => String::new(),
+            }),
+            Attribute::RelPath => Value::String(f.relpath().display().to_string()),
+            Attribute::Depth => Value::Int(f.depth.try_into().unwrap()),
+            Attribute::Xattrs => Value::String(
+                f.get_xattrs()?
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            Attribute::Target => Value::String(match std::fs::read_link(&f.path) {
+                Ok(target) => target.display().to_string(),
+                Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => String::new(),
+                Err(e) => return Err((&e).into()),
+            }),
+            // Like `find -xtype l`, a symlink that can't be followed for any reason, such as a
+            // loop, counts as broken:
+            Attribute::Broken => {
+                Value::Boolean(f.path.is_symlink() && std::fs::metadata(&f.path).is_err())
+            }
+            Attribute::Lines => Value::Int(f.get_lines()?.try_into().unwrap()),
+            Attribute::IsText => Value::Boolean(f.get_is_text()?),
+            Attribute::IsBinary => Value::Boolean(f.is_regular()? && !f.get_is_text()?),
+            Attribute::Readable => Value::Boolean(f.get_access(Access::Read)?),
+            Attribute::Writable => Value::Boolean(f.get_access(Access::Write)?),
+            Attribute::Executable => Value::Boolean(f.get_access(Access::Execute)?),
+            Attribute::Mime | Attribute::Magic => Value::String(match f.get_head()? {
+                Some(head) => {
+                    let magic = crate::magic::identify(head);
+                    match self {
+                        Attribute::Mime => magic.mime.to_string(),
+                        _ => magic.description,
+                    }
+                }
+                None => String::new(),
+            }),
             _ => self.evaluate_needs_stat(f)?,
         })
     }
@@ -274,37 +691,116 @@ impl Attribute {
             Attribute::Dev => SpecialValue::new(md.dev(), SpecialValueKind::Devno),
             Attribute::RDev => SpecialValue::new(md.rdev(), SpecialValueKind::Devno),
             Attribute::Mode => SpecialValue::new(md.mode().into(), SpecialValueKind::Mode),
+            Attribute::Perms => Value::String(crate::perm::render(md.mode())),
+            Attribute::FsType => {
+                // statfs() follows symlinks, but a symlink is on the same filesystem as the
+                // directory containing it:
+                let path = match md.is_symlink() {
+                    true => containing_dir(&f.path),
+                    false => &f.path,
+                };
+                Value::String(
+                    crate::filesystem::fs_type(path, md.dev())
+                        .map_err(|e| crate::Error::from(&e))?,
+                )
+            }
+            Attribute::Mountpoint => Value::Boolean(match md.mount_root() {
+                Some(mount_root) => mount_root,
+                // Without help from the kernel, a mount's root is on a different device to its
+                // parent, or for "/", is its own parent:
+                None if md.is_symlink() => false,
+                None => {
+                    // "dir/.." is right for a directory, even "." or "/", but the kernel resolves
+                    // "file/.." through the file, which fails unless it's a directory:
+                    let parent = match md.is_dir() {
+                        true => f.path.join(".."),
+                        false => containing_dir(&f.path).to_path_buf(),
+                    };
+                    let parent = Metadata::statx(&parent).map_err(|e| crate::Error::from(&e))?;
+                    parent.dev() != md.dev() || parent.ino() == md.ino()
+                }
+            }),
             Attribute::Size => Value::Int(md.size().try_into().unwrap()),
             Attribute::NLink => Value::Int(md.nlink().try_into().unwrap()),
             Attribute::Owner => SpecialValue::new(md.uid().into(), SpecialValueKind::Uid),
-            Attribute::Group => SpecialValue::new(md.gid().into(), SpecialValueKind::Uid),
-            Attribute::Atime => Value::Int(md.atime()),
-            Attribute::Ctime => Value::Int(md.ctime()),
-            Attribute::Mtime => Value::Int(md.mtime()),
-            // XXX: make type return an enum instead of a string
-            Attribute::Type => {
-                let ty = md.file_type();
-                Value::String(
-                    if ty.is_dir() {
-                        "dir"
-                    } else if ty.is_file() {
-                        "file"
-                    } else if ty.is_block_device() {
-                        "block"
-                    } else if ty.is_char_device() {
-                        "char"
-                    } else if ty.is_fifo() {
-                        "fifo"
-                    } else if ty.is_socket() {
-                        "socket"
-                    } else {
-                        "unknown"
-                    }
-                    .to_string(),
-                )
+            Attribute::Group => SpecialValue::new(md.gid().into(), SpecialValueKind::Gid),
+            Attribute::OwnerName => Value::String(match crate::users::user_name(md.uid()) {
+                Some(name) => name,
+                None => md.uid().to_string(),
+            }),
+            Attribute::GroupName => Value::String(match crate::users::group_name(md.gid()) {
+                Some(name) => name,
+                None => md.gid().to_string(),
+            }),
+            Attribute::Atime => SpecialValue::new_time(md.atime(), md.atime_nsec()),
+            Attribute::Ctime => SpecialValue::new_time(md.ctime(), md.ctime_nsec()),
+            Attribute::Mtime => SpecialValue::new_time(md.mtime(), md.mtime_nsec()),
+            Attribute::AtimeNs => Value::Int(nanoseconds(md.atime(), md.atime_nsec())),
+            Attribute::CtimeNs => Value::Int(nanoseconds(md.ctime(), md.ctime_nsec())),
+            Attribute::MtimeNs => Value::Int(nanoseconds(md.mtime(), md.mtime_nsec())),
+            Attribute::Btime => {
+                let (secs, nsecs) = md.btime()?;
+                SpecialValue::new_time(secs, nsecs)
             }
-            Attribute::Name => unreachable!(),
-            Attribute::Path => unreachable!(),
+            Attribute::Attributes => Value::String(md.attributes()),
+            Attribute::MntId => Value::Int(md.mnt_id()?.try_into().unwrap()),
+            Attribute::StatxMask => Value::String(md.mask_names()),
+            Attribute::AAge | Attribute::MAge | Attribute::CAge => {
+                unreachable!("Age attributes are compiled into a subtraction from `now`.")
+            }
+            // XXX: make type return an enum instead of a string
+            Attribute::Type => Value::String(
+                if md.is_dir() {
+                    "dir"
+                } else if md.is_file() {
+                    "file"
+                } else if md.is_symlink() {
+                    "link"
+                } else if md.is_block_device() {
+                    "block"
+                } else if md.is_char_device() {
+                    "char"
+                } else if md.is_fifo() {
+                    "fifo"
+                } else if md.is_socket() {
+                    "socket"
+                } else {
+                    "unknown"
+                }
+                .to_string(),
+            ),
+            Attribute::Name
+            | Attribute::Path
+            | Attribute::Ext
+            | Attribute::Stem
+            | Attribute::Parent
+            | Attribute::RelPath
+            | Attribute::Depth
+            | Attribute::Xattrs
+            | Attribute::Target
+            | Attribute::Broken
+            | Attribute::Lines
+            | Attribute::IsText
+            | Attribute::IsBinary
+            | Attribute::Readable
+            | Attribute::Writable
+            | Attribute::Executable
+            | Attribute::Mime
+            | Attribute::Magic => unreachable!(),
         })
     }
 }
+
+/// The directory containing the file at `path`, which is "." for a relative path with only one
+/// component.
+fn containing_dir(path: &std::path::Path) -> &std::path::Path {
+    match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => std::path::Path::new("."),
+    }
+}
+
+/// Combines a timestamp from stat() into nanoseconds since the epoch.
+fn nanoseconds(secs: i64, nsecs: i64) -> i64 {
+    secs.saturating_mul(NANOS_PER_SEC).saturating_add(nsecs)
+}
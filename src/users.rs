@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use nix::unistd::{Gid, Group, Uid, User};
+
+/// Looking up a user or group can mean reading /etc/passwd or asking a directory service, and the
+/// same few owners come up over and over in a walk, so lookups are cached for the whole process
+/// and shared between worker threads. Names that don't exist are cached too.
+#[derive(Default)]
+struct Cache {
+    user_names: HashMap<u32, Option<String>>,
+    uids: HashMap<String, Option<u32>>,
+    group_names: HashMap<u32, Option<String>>,
+    gids: HashMap<String, Option<u32>>,
+}
+
+static CACHE: LazyLock<Mutex<Cache>> = LazyLock::new(Default::default);
+
+/// Looks up `key` in the map chosen by `map`, calling `lookup` if it isn't cached. The lock isn't
+/// held during the lookup, so that threads looking up different names don't wait on each other.
+fn cached<K, V>(
+    map: fn(&mut Cache) -> &mut HashMap<K, Option<V>>,
+    key: &K,
+    lookup: impl FnOnce() -> Option<V>,
+) -> Option<V>
+where
+    K: std::hash::Hash + Eq + Clone,
+    V: Clone,
+{
+    if let Some(value) = map(&mut CACHE.lock().unwrap()).get(key) {
+        return value.clone();
+    }
+
+    let value = lookup();
+    map(&mut CACHE.lock().unwrap()).insert(key.clone(), value.clone());
+    value
+}
+
+/// The name of the user with ID `uid`, if there is one.
+pub fn user_name(uid: u32) -> Option<String> {
+    cached(
+        |c| &mut c.user_names,
+        &uid,
+        || Some(User::from_uid(Uid::from_raw(uid)).ok()??.name),
+    )
+}
+
+/// The ID of the user called `name`, if there is one. Like chown(1), a name that is all digits
+/// and isn't a user's name is taken as an ID.
+pub fn uid(name: &str) -> Option<u32> {
+    cached(
+        |c| &mut c.uids,
+        &name.to_string(),
+        || match User::from_name(name) {
+            Ok(Some(user)) => Some(user.uid.as_raw()),
+            _ => parse_id(name),
+        },
+    )
+}
+
+/// The name of the group with ID `gid`, if there is one.
+pub fn group_name(gid: u32) -> Option<String> {
+    cached(
+        |c| &mut c.group_names,
+        &gid,
+        || Some(Group::from_gid(Gid::from_raw(gid)).ok()??.name),
+    )
+}
+
+/// The ID of the group called `name`, if there is one. As for users, a name that is all digits
+/// is taken as an ID if it isn't a group's name.
+pub fn gid(name: &str) -> Option<u32> {
+    cached(
+        |c| &mut c.gids,
+        &name.to_string(),
+        || match Group::from_name(name) {
+            Ok(Some(group)) => Some(group.gid.as_raw()),
+            _ => parse_id(name),
+        },
+    )
+}
+
+fn parse_id(name: &str) -> Option<u32> {
+    match !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit()) {
+        true => name.parse().ok(),
+        false => None,
+    }
+}
@@ -13,6 +13,8 @@ pub enum Variable {
     /// variable type. Accessing a NotYetKnown variable during runtime is a bug.
     NotYetKnown(String),
     Scalar(Identifier),
+    /// A function parameter, which is an index into the function call's `Frame`.
+    Local(usize),
     Arr(usize),
     ArrSub(ArraySubscript),
 }
@@ -28,6 +30,7 @@ impl std::fmt::Display for Variable {
         match self {
             Variable::NotYetKnown(name) => write!(f, "TypeUnknown(\"{name}\")"),
             Variable::Scalar(id) => write!(f, "Var({})", id.id),
+            Variable::Local(id) => write!(f, "Local({})", id),
             Variable::Arr(id) => write!(f, "Array({})", id),
             Variable::ArrSub(arr) => write!(f, "Array({})[{}]", arr.id, arr.subscript),
         }
@@ -86,6 +89,34 @@ impl VariableState<'_> {
             VariableState::Unlocked(_) => panic!("Cannot assign to unlocked variable"),
         }
     }
+
+    /// Assign an already-evaluated value `new` to the variable `assignee`.
+    pub fn set_variable(
+        &self,
+        assignee: &Variable,
+        f: Option<&FileState>,
+        new: Value,
+    ) -> crate::Result<()> {
+        self.set_variable_expression(assignee, f, &Expression::Atom(new))
+    }
+
+    /// Replace the contents of the array `id` with `entries`.
+    pub fn set_array(&self, id: usize, entries: HashMap<Value, Value>) {
+        match self {
+            VariableState::Locked(l) => l.arrays.lock().unwrap().arrs[id] = entries,
+            VariableState::Unlocked(_) => panic!("Cannot assign to unlocked variable"),
+        }
+    }
+
+    /// Get a snapshot of the keys of the array `id`.
+    ///
+    /// The keys are copied so that the caller doesn't hold the arrays' lock while using them.
+    pub fn array_keys(&self, id: usize) -> Vec<Value> {
+        match self {
+            VariableState::Locked(l) => l.arrays.lock().unwrap().keys(id),
+            VariableState::Unlocked(u) => u.arrays.keys(id),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -101,6 +132,7 @@ impl UnlockedVars<'_> {
                 panic!("Attempted to use unresolved variable \"{name}\".")
             }
             Variable::Scalar(id) => self.scalars[id.id].clone(),
+            Variable::Local(_) => return Err(unbound_local()),
             // XXX: should evaluating an array to a string be allowed in a RHS?
             Variable::Arr(_) => panic!("Cannot evaluate an array name in this context."),
             Variable::ArrSub(arr) => {
@@ -135,6 +167,7 @@ impl LockedVars {
                 let scalars = self.scalars.lock().unwrap();
                 scalars[id.id].clone()
             }
+            Variable::Local(_) => return Err(unbound_local()),
             Variable::Arr(id) => {
                 let arrays = self.arrays.lock().unwrap();
                 Value::String(arrays.array_to_string(*id))
@@ -182,6 +215,7 @@ impl LockedVars {
                     .evaluate(f, &VariableState::Unlocked(unlocked))?;
                 arrays.set_variable(arr.id, subscript, new);
             }
+            Variable::Local(_) => return Err(unbound_local()),
             Variable::Arr(_) => panic!("Cannot assign to an array name"),
         };
 
@@ -234,8 +268,18 @@ impl Arrays {
         )
     }
 
+    fn keys(&self, id: usize) -> Vec<Value> {
+        self.arrs[id].keys().cloned().collect()
+    }
+
     /// Sets a value in an associative array.
     fn set_variable(&mut self, id: usize, subscript: Value, new: Value) {
         self.arrs[id].entry(subscript).insert_entry(new);
     }
 }
+
+/// The error for a local variable that reached evaluation without being bound to its value first,
+/// which would be a bug; see `Expression::bind()`.
+fn unbound_local() -> crate::Error {
+    crate::RuntimeError::new("Local variables must be bound before evaluation").into()
+}
@@ -0,0 +1,82 @@
+use std::ffi::{CStr, CString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// A file's extended attributes, as pairs of names and values.
+pub type Xattrs = Vec<(String, Vec<u8>)>;
+
+/// Reads all of the extended attributes of the file at `path`, following symlinks.
+///
+/// A filesystem that doesn't support extended attributes is treated as if the file has none.
+pub fn read_all(path: &Path) -> std::io::Result<Xattrs> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+
+    let names = match read_buffer(|buf, size| {
+        // SAFETY: `path` is a valid C string, and `buf` has room for `size` bytes.
+        unsafe { libc::listxattr(path.as_ptr(), buf.cast(), size) }
+    }) {
+        Ok(names) => names,
+        Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut xattrs = Vec::new();
+    // The names are a sequence of NUL-terminated strings:
+    for name in names.split_inclusive(|b| *b == 0) {
+        let name = CStr::from_bytes_with_nul(name).map_err(std::io::Error::other)?;
+        match get_cstr(&path, name)? {
+            Some(value) => xattrs.push((name.to_string_lossy().to_string(), value)),
+            // The attribute was removed since it was listed:
+            None => continue,
+        }
+    }
+
+    Ok(xattrs)
+}
+
+/// Reads the extended attribute `name` of the file at `path`, following symlinks. Returns None if
+/// the file doesn't have that attribute.
+pub fn get(path: &Path, name: &str) -> std::io::Result<Option<Vec<u8>>> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let name = CString::new(name)?;
+    get_cstr(&path, &name)
+}
+
+fn get_cstr(path: &CStr, name: &CStr) -> std::io::Result<Option<Vec<u8>>> {
+    match read_buffer(|buf, size| {
+        // SAFETY: `path` and `name` are valid C strings, and `buf` has room for `size` bytes.
+        unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), buf.cast(), size) }
+    }) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.raw_os_error() == Some(libc::ENODATA) => Ok(None),
+        Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Calls an xattr syscall that fills in a buffer, first with an empty buffer to find the size
+/// needed. If the value grows in between the two calls, the syscall fails with ERANGE, so try
+/// again.
+fn read_buffer<F>(f: F) -> std::io::Result<Vec<u8>>
+where
+    F: Fn(*mut u8, libc::size_t) -> libc::ssize_t,
+{
+    loop {
+        let size = f(std::ptr::null_mut(), 0);
+        if size < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let mut buf = vec![0; size as usize];
+        let size = f(buf.as_mut_ptr(), buf.len());
+        if size >= 0 {
+            buf.truncate(size as usize);
+            return Ok(buf);
+        }
+
+        let e = std::io::Error::last_os_error();
+        if e.raw_os_error() != Some(libc::ERANGE) {
+            return Err(e);
+        }
+    }
+}
@@ -3,6 +3,7 @@ use puffin::Args;
 
 /// Create a test state for `test_name` that runs the program `prog` and expects it to error.
 /// Metadata`md`.
+#[allow(clippy::needless_borrows_for_generic_args)]
 fn should_runtime_error(test_name: &str, prog: &str) {
     let state = TestState::setup(test_name).unwrap();
 
@@ -14,6 +15,9 @@ fn should_runtime_error(test_name: &str, prog: &str) {
         path: path.clone(),
         prog: prog.to_string(),
         n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
     };
 
     let mut buf = Buffer::new();
@@ -28,4 +32,89 @@ fn special_values_invalid() {
     should_runtime_error("special_values_invalid", "{ print .owner + 1 }");
     should_runtime_error("special_values_invalid", "{ print .owner + .ino }");
     should_runtime_error("special_values_invalid", "{ arr[1] = .ino; arr[1] += 1 }");
+    should_runtime_error("special_values_invalid", "{ print -.ino }");
+    should_runtime_error("special_values_invalid", "{ print !.owner }");
+    should_runtime_error("special_values_invalid", "{ print .ino < .ino }");
+    should_runtime_error("special_values_invalid", "{ print .ino != \"root\" }");
+}
+
+#[test]
+fn time_errors() {
+    should_runtime_error("time_errors", "{ print .mtime + .mtime }");
+    should_runtime_error("time_errors", "{ print .mtime * 2 }");
+    should_runtime_error("time_errors", "{ print .mtime < .ino }");
+    should_runtime_error("time_errors", "{ print strftime(\"%Y\", .ino) }");
+    should_runtime_error("time_errors", "{ d = \"2024-13-01\"; print date(d) }");
+}
+
+#[test]
+fn division_by_zero() {
+    should_runtime_error("division_by_zero", "{ print .size / 0 }");
+    should_runtime_error("division_by_zero", "{ x = 0; print 10 % x }");
+}
+
+#[test]
+fn integer_overflow() {
+    for prog in [
+        "{ print -(-9223372036854775807 - 1) }",
+        "{ print 9223372036854775807 + 1 }",
+        "{ print -9223372036854775807 - 2 }",
+        "{ print 9223372036854775807 * 2 }",
+        "{ print (-9223372036854775807 - 1) / -1 }",
+        "{ x = 9223372036854775807; x += 1 }",
+    ] {
+        should_runtime_error("integer_overflow", prog);
+    }
+}
+
+#[test]
+fn invalid_regex() {
+    should_runtime_error("invalid_regex", "begin { pat = \"(\" } .name ~ pat");
+    should_runtime_error(
+        "invalid_regex",
+        "begin { pat = \"(\" } { print sub(pat, \"x\", .name) }",
+    );
+}
+
+#[test]
+fn infinite_recursion() {
+    should_runtime_error(
+        "infinite_recursion",
+        "function f(n) { return f(n + 1) } { print f(1) }",
+    );
+    should_runtime_error(
+        "infinite_recursion",
+        "function depth(n) { if (n <= 1) { return 1 } return 1 + depth(n - 1) } { print depth(1001) }",
+    );
+}
+
+#[test]
+fn printf_dynamic_format() {
+    should_runtime_error(
+        "printf_dynamic_format",
+        "begin { fmt = \"%d %d\" } { printf fmt, 1 }",
+    );
+    should_runtime_error(
+        "printf_dynamic_format",
+        "begin { fmt = \"%z\" } { printf fmt, 1 }",
+    );
+}
+
+#[test]
+fn hash_errors() {
+    should_runtime_error("hash_errors", "{ a = \"crc32\"; print hash(.path, a) }");
+}
+
+#[test]
+fn permission_errors() {
+    should_runtime_error("permission_errors", "{ s = \"o+q\"; print perm(.mode, s) }");
+    should_runtime_error("permission_errors", "{ print perm(.ino, \"o+w\") }");
+    should_runtime_error("permission_errors", "{ print .ino & 1 }");
+    should_runtime_error("permission_errors", "{ print .mode + 1 }");
+}
+
+#[test]
+fn device_number_errors() {
+    should_runtime_error("device_number_errors", "{ print major(.ino) }");
+    should_runtime_error("device_number_errors", "{ print minor(-1) }");
 }
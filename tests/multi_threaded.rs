@@ -13,6 +13,9 @@ fn count_files_1() {
         path: dir,
         prog: "{ numfiles += 1 } end { print numfiles }".to_string(),
         n_threads: 8,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
     };
 
     let mut buf = Buffer::new();
@@ -36,6 +39,9 @@ fn count_files_2() {
         path: dir,
         prog: "{ numfiles = numfiles + 1 } end { print numfiles }".to_string(),
         n_threads: 8,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
     };
 
     let mut buf = Buffer::new();
@@ -65,6 +71,9 @@ fn count_files_by_size() {
         path: dir,
         prog: ".size <= 3 { numfiles += 1 } end {print numfiles }".to_string(),
         n_threads: 8,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
     };
 
     let mut buf = Buffer::new();
@@ -94,6 +103,9 @@ fn count_files_arrays() {
         path: dir.clone(),
         prog: ".size == 3 { arr[\"three\"] += 1} .size == 2 { arr[\"two\"] += 1 }  end {print arr[\"three\"], arr[\"two\"]  }".to_string(),
         n_threads: 8,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
     };
 
     let mut buf = Buffer::new();
@@ -107,6 +119,9 @@ fn count_files_arrays() {
         prog: ".size == 3 { arr[\"three\"] += 1} .size == 2 { arr[\"two\"] += 1 }  end {print arr}"
             .to_string(),
         n_threads: 8,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
     };
 
     let mut buf = Buffer::new();
@@ -131,6 +146,9 @@ fn count_files_arrays_2() {
         path: dir,
         prog: "{ arr[\"numfiles\"] += 1; arr2[\"dup\"] = arr[\"numfiles\"] } end { print arr[\"numfiles\"], arr2[\"dup\"]  }".to_string(),
         n_threads: 8,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
     };
 
     let mut buf = Buffer::new();
@@ -141,3 +159,198 @@ fn count_files_arrays_2() {
 
     state.cleanup();
 }
+
+#[test]
+fn iterate_arrays() {
+    let state = TestState::setup("iterate_arrays").unwrap();
+
+    state
+        .make_tree("size_2", 2, 2, 2, Some(Metadata { size: 2 }))
+        .unwrap();
+
+    state
+        .make_tree("size_3", 2, 2, 3, Some(Metadata { size: 3 }))
+        .unwrap();
+
+    let dir = state.test_subdir();
+
+    let args = Args {
+        path: dir,
+        prog: ".type == \"file\" { count[.size] += 1; bytes[.size] += .size } end { for (k in count) { print k, count[k], bytes[k] } }".to_string(),
+        n_threads: 8,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
+    };
+
+    let mut buf = Buffer::new();
+    puffin::driver(&args, &mut buf).unwrap();
+
+    let lines = buf.sorted_lines();
+    assert_eq!(lines, vec!["2 12 24", "3 18 54"]);
+
+    state.cleanup();
+}
+
+#[test]
+fn count_files_functions() {
+    let state = TestState::setup("count_files_functions").unwrap();
+
+    state
+        .make_tree("size_2", 2, 2, 2, Some(Metadata { size: 2 }))
+        .unwrap();
+
+    state
+        .make_tree("size_3", 2, 2, 3, Some(Metadata { size: 3 }))
+        .unwrap();
+
+    let dir = state.test_subdir();
+
+    let args = Args {
+        path: dir,
+        prog: "function bucket(n) { if (n > 2) { return \"big\" } return \"small\" } .type == \"file\" { hist[bucket(.size)] += 1 } end { print hist[\"big\"], hist[\"small\"] }".to_string(),
+        n_threads: 8,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
+    };
+
+    let mut buf = Buffer::new();
+    puffin::driver(&args, &mut buf).unwrap();
+
+    buf.trim_newline();
+    assert_eq!(buf, "18 12");
+
+    state.cleanup();
+}
+
+#[test]
+fn group_by_top_level_directory() {
+    let state = TestState::setup("group_by_top_level_directory").unwrap();
+
+    state
+        .make_tree("tree", 2, 2, 1, Some(Metadata { size: 10 }))
+        .unwrap();
+
+    let dir = state.get_path("tree");
+
+    for n_threads in [1, 8] {
+        let args = Args {
+            path: dir.clone(),
+            prog: "
+                .depth == 0 { print \"root\", .relpath }
+                .depth == 2 { deep += 1 }
+                .type == \"file\" { usage[relpath_component(1)] += .size }
+                end { print \"deep\", deep; for (k in usage) { print k, usage[k] } }"
+                .to_string(),
+            n_threads,
+            follow: false,
+            max_bytes: None,
+            effective_ids: false,
+        };
+
+        let mut buf = Buffer::new();
+        puffin::driver(&args, &mut buf).unwrap();
+
+        let lines = buf.sorted_lines();
+        assert_eq!(
+            lines,
+            vec!["deep 6", "root .", "subdir_0 30", "subdir_1 30"]
+        );
+    }
+
+    state.cleanup();
+}
+
+#[test]
+fn symlinks() {
+    use std::os::unix::fs::symlink;
+
+    let state = TestState::setup("symlinks").unwrap();
+
+    state.make_tree("tree", 1, 1, 1, None).unwrap();
+    state.create_file("tree/file_0", None).unwrap();
+    let dir = state.get_path("tree");
+    symlink("subdir_0", dir.join("dir_link")).unwrap();
+    symlink("file_0", dir.join("file_link")).unwrap();
+    symlink("missing", dir.join("broken_link")).unwrap();
+    symlink("..", dir.join("subdir_0").join("loop")).unwrap();
+
+    let prog = ".depth > 0 { print .relpath, .type, .target, .broken }";
+
+    for n_threads in [1, 8] {
+        let args = Args {
+            path: dir.clone(),
+            prog: prog.to_string(),
+            n_threads,
+            follow: false,
+            max_bytes: None,
+            effective_ids: false,
+        };
+
+        let mut buf = Buffer::new();
+        puffin::driver(&args, &mut buf).unwrap();
+
+        assert_eq!(
+            buf.sorted_lines(),
+            vec![
+                "broken_link link missing True",
+                "dir_link link subdir_0 False",
+                "file_0 file  False",
+                "file_link link file_0 False",
+                "subdir_0 dir  False",
+                "subdir_0/file_0 file  False",
+                "subdir_0/loop link .. False",
+            ]
+        );
+
+        // When following symlinks, dir_link is walked like subdir_0, but neither loop leads
+        // back into the tree:
+        let args = Args {
+            path: dir.clone(),
+            prog: prog.to_string(),
+            n_threads,
+            follow: true,
+            max_bytes: None,
+            effective_ids: false,
+        };
+
+        let mut buf = Buffer::new();
+        puffin::driver(&args, &mut buf).unwrap();
+
+        assert_eq!(
+            buf.sorted_lines(),
+            vec![
+                "broken_link link missing True",
+                "dir_link dir subdir_0 False",
+                "dir_link/file_0 file  False",
+                "dir_link/loop dir .. False",
+                "file_0 file  False",
+                "file_link file file_0 False",
+                "subdir_0 dir  False",
+                "subdir_0/file_0 file  False",
+                "subdir_0/loop dir .. False",
+            ]
+        );
+
+        // Like `find -H`, a symlink given as the root is followed without following others:
+        let args = Args {
+            path: dir.join("dir_link"),
+            prog: "{ print .relpath, .type }".to_string(),
+            n_threads,
+            follow: false,
+            max_bytes: None,
+            effective_ids: false,
+        };
+
+        let mut buf = Buffer::new();
+        puffin::driver(&args, &mut buf).unwrap();
+
+        assert_eq!(
+            buf.sorted_lines(),
+            vec![". dir", "file_0 file", "loop link"]
+        );
+    }
+
+    state.cleanup();
+}
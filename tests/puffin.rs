@@ -13,6 +13,7 @@ enum ExpectedOutput<'a> {
 ///
 /// If `expected_output` is a string, then it is compared to the program output. Otherwise,
 /// compare against the name of the created file.
+#[allow(clippy::needless_borrows_for_generic_args)]
 fn test_one_file_with_program(
     test_name: &str,
     md: Option<Metadata>,
@@ -27,6 +28,9 @@ fn test_one_file_with_program(
         path: path.clone(),
         prog: prog.to_string(),
         n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
     };
 
     let mut buf = Buffer::new();
@@ -57,6 +61,9 @@ fn empty_program() {
         path: dir,
         prog: prog.to_string(),
         n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
     };
 
     let mut buf = Buffer::new();
@@ -196,6 +203,7 @@ fn strings() {
 }
 
 #[test]
+#[allow(clippy::needless_borrows_for_generic_args, clippy::useless_format)]
 fn print_paths() {
     let state = TestState::setup("print_paths").unwrap();
 
@@ -205,6 +213,9 @@ fn print_paths() {
         path: path.clone(),
         prog: "{ print .name }".to_string(),
         n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
     };
 
     let mut buf = Buffer::new();
@@ -216,6 +227,9 @@ fn print_paths() {
         path: path.clone(),
         prog: "{ print .path }".to_string(),
         n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
     };
 
     let mut buf = Buffer::new();
@@ -346,3 +360,963 @@ fn expressions() {
     expect_output("{print (1 < 2) * 3}", "3");
     expect_output("{print (((1 + 2) * 3) - 1) * 5 }", "40");
 }
+
+#[test]
+fn regex_match() {
+    fn expect_output(prog: &str, output: ExpectedOutput) {
+        test_one_file_with_program("regex_match", None, prog, output);
+    }
+
+    expect_output(".name ~ \"-file$\"", ExpectedOutput::Filename);
+    expect_output(".name ~ \"^regex\\.\"", ExpectedOutput::String(""));
+    expect_output(".name !~ \"-file$\"", ExpectedOutput::String(""));
+    expect_output(".name !~ \"\\.rs$\"", ExpectedOutput::Filename);
+    expect_output(
+        "begin { pat = \"^re.ex_\" } .name ~ pat { print \"match\" }",
+        ExpectedOutput::String("match\n"),
+    );
+    expect_output(
+        "{ print \"abc\" ~ \"b\", 12 ~ \"^1\", \"abc\" !~ \"b\" }",
+        ExpectedOutput::String("True True False\n"),
+    );
+}
+
+#[test]
+fn glob_match() {
+    fn expect_output(prog: &str, output: ExpectedOutput) {
+        test_one_file_with_program("glob_match", None, prog, output);
+    }
+
+    expect_output(".name like \"*-file\"", ExpectedOutput::Filename);
+    expect_output(".name like \"*.log\"", ExpectedOutput::String(""));
+    expect_output(".name like \"glob_?atch-*\"", ExpectedOutput::Filename);
+    expect_output(
+        ".name like \"glob_[a-l]atch-*\"",
+        ExpectedOutput::String(""),
+    );
+    expect_output(".name like \"GLOB_*\"", ExpectedOutput::String(""));
+    expect_output(".name ilike \"GLOB_*\"", ExpectedOutput::Filename);
+    expect_output(".path like \"*-file\"", ExpectedOutput::String(""));
+    expect_output(
+        ".path like \"**/glob_match-file\"",
+        ExpectedOutput::Filename,
+    );
+    expect_output(
+        "begin { pat = \"*-FILE\" } .name ilike pat { print \"match\" }",
+        ExpectedOutput::String("match\n"),
+    );
+}
+
+#[test]
+fn if_statements() {
+    fn expect_output(prog: &str, output: &str) {
+        test_one_file_with_program(
+            "if_statements",
+            Some(Metadata { size: 42 }),
+            prog,
+            ExpectedOutput::String(output),
+        );
+    }
+
+    expect_output("{ if (.size > 10) { print \"big\" } }", "big\n");
+    expect_output("{ if (.size > 100) { print \"big\" } }", "");
+    expect_output(
+        "{ if (.size > 100) { print \"big\" } else { print \"small\" } }",
+        "small\n",
+    );
+    expect_output(
+        "{ if (.size > 100) { print \"big\" } else if (.size > 10) { print \"medium\" } else { print \"small\" } }",
+        "medium\n",
+    );
+    expect_output(
+        "{ if (.size == 42) { x = 1; print \"yes\" } print x; if (0) {} else {} }",
+        "yes\n1\n",
+    );
+    expect_output(
+        "begin { if (1) { if (0) { print 1 } else { print 2 } } } 0",
+        "2\n",
+    );
+}
+
+#[test]
+fn loops() {
+    fn expect_output(prog: &str, output: &str) {
+        test_one_file_with_program(
+            "loops",
+            Some(Metadata { size: 3 }),
+            prog,
+            ExpectedOutput::String(output),
+        );
+    }
+
+    expect_output(
+        "0 {} end { i = 0; while (i < 3) { print i; i += 1 } }",
+        "0\n1\n2\n",
+    );
+    expect_output("{ i = 0; while (i < .size) { i += 1 } print i } 0", "3\n");
+    expect_output(
+        "{ for (i = 0; i < .size; i += 1) { print i } }",
+        "0\n1\n2\n",
+    );
+    expect_output(
+        "begin { for (i = 0; i < 10; i += 1) { if (i == 1) { continue } if (i > 3) { break } print i } } 0",
+        "0\n2\n3\n",
+    );
+    expect_output(
+        "0 {} end { i = 0; for (;;) { i += 1; if (i >= 5) { break } } print i }",
+        "5\n",
+    );
+    expect_output(
+        "0 {} end { i = 0; while (i < 2) { j = 0; while (1) { j += 1; if (j > 2) { break } } i += 1; print i, j } }",
+        "1 3\n2 3\n",
+    );
+    expect_output(
+        "0 {} end { for (i = 0; i < 3; i += 1) { hist[i * 10] = i } print hist[10], hist[20] }",
+        "1 2\n",
+    );
+}
+
+#[test]
+fn for_in_loops() {
+    fn expect_output(prog: &str, output: &str) {
+        test_one_file_with_program("for_in_loops", None, prog, ExpectedOutput::String(output));
+    }
+
+    expect_output(
+        "begin { arr[\"a\"] = 1; for (k in arr) { print k, arr[k] } } 0",
+        "a 1\n",
+    );
+    expect_output("begin { for (k in arr) { print k } } 0", "");
+    expect_output(
+        "begin { arr[1] = 1; arr[2] = 2; arr[3] = 3; for (k in arr) { tot += arr[k]; arr[k] = 0 } print tot, arr[1] + arr[2] + arr[3] } 0",
+        "6 0\n",
+    );
+    expect_output(
+        "begin { arr[1] = 1; arr[2] = 2; for (k in arr) { n += 1; break } print n } 0",
+        "1\n",
+    );
+}
+
+#[test]
+fn functions() {
+    fn expect_output(prog: &str, output: &str) {
+        test_one_file_with_program(
+            "functions",
+            Some(Metadata { size: 42 }),
+            prog,
+            ExpectedOutput::String(output),
+        );
+    }
+
+    expect_output(
+        "function add(a, b) { return a + b } { print add(1, 2) }",
+        "3\n",
+    );
+    expect_output(
+        "{ print double(.size) } function double(n) { return n * 2 }",
+        "84\n",
+    );
+    expect_output(
+        "function bucket(n) { if (n < 10) { return \"small\" } return \"large\" } { print bucket(.size), bucket(1) }",
+        "large small\n",
+    );
+    // Parameters are local to the call and don't clobber globals of the same name:
+    expect_output(
+        "function f(x) { x = x + 1; return x } { x = 10; print f(1), x }",
+        "2 10\n",
+    );
+    // Extra parameters are locals, and other variables are globals:
+    expect_output(
+        "function f(a, tmp) { tmp = a * 2; g = tmp; return tmp } { print f(3), tmp, g }",
+        "6 0 6\n",
+    );
+    expect_output(
+        "function fact(n) { if (n <= 1) { return 1 } return n * fact(n - 1) } { print fact(10) }",
+        "3628800\n",
+    );
+    expect_output(
+        "function fib(n) { if (n < 2) { return n } return fib(n - 1) + fib(n - 2) } { print fib(15) }",
+        "610\n",
+    );
+    expect_output(
+        "function hi() { print \"hi\" } { x = hi(); print x }",
+        "hi\n0\n",
+    );
+    expect_output(
+        "function first(n, i) { for (i = 0; i < 10; i += 1) { if (i * i > n) { return i } } return 100 } { print first(.size) }",
+        "7\n",
+    );
+    expect_output(
+        "function count(k) { n += 1; return k } { arr[count(1)] += .size; arr[count(1)] += 1 } end { print arr[1], n }",
+        "43 2\n",
+    );
+    expect_output(
+        "function big(n) { return n > 10 } big(.size) { print \"big\" }",
+        "big\n",
+    );
+    // Recursion can go as deep as the call depth limit of 1000:
+    expect_output(
+        "function depth(n) { if (n <= 1) { return 1 } return 1 + depth(n - 1) } begin { print depth(1000) } { print depth(1000) }",
+        "1000\n1000\n",
+    );
+    // The right side of `and` and `or` is only called if it's needed:
+    expect_output(
+        "function sum(n) { return n <= 0 or sum(n - 1) } { print sum(5) }",
+        "True\n",
+    );
+    expect_output(
+        "function hi() { print \"hi\"; return 1 } { print 0 and hi(); print 1 or hi(); print 1 and hi() }",
+        "False\nTrue\nhi\nTrue\n",
+    );
+    expect_output(
+        "{ x = 0 and split(\"a b\", arr); n = 0; for (k in arr) { n += 1 } print x, n }",
+        "False 0\n",
+    );
+}
+
+#[test]
+fn multiple_begin_and_end() {
+    fn expect_output(prog: &str, output: &str) {
+        test_one_file_with_program(
+            "multiple_begin_and_end",
+            None,
+            prog,
+            ExpectedOutput::String(output),
+        );
+    }
+
+    expect_output(
+        "begin { print 1 } begin { print 2 } { print 3 } end { print 4 } end { print 5 }",
+        "1\n2\n3\n4\n5\n",
+    );
+    expect_output(
+        "end { print x } begin { x = 1 } { x += 1 } begin { x = x * 10 } end { print x + 1 }",
+        "11\n12\n",
+    );
+}
+
+#[test]
+fn printf() {
+    fn expect_output(prog: &str, output: &str) {
+        test_one_file_with_program(
+            "printf",
+            Some(Metadata { size: 42 }),
+            prog,
+            ExpectedOutput::String(output),
+        );
+    }
+
+    expect_output("{ printf \"%d bytes\\n\", .size }", "42 bytes\n");
+    expect_output("{ printf \"%s\", \"no newline\" }", "no newline");
+    expect_output(
+        "{ printf \"[%-8s|%6d|%06d|%x|%o]\\n\", \"ab\", .size, .size, .size, .size }",
+        "[ab      |    42|000042|2a|52]\n",
+    );
+    expect_output(
+        "{ s = sprintf(\"%5.2s|%.3d\", \"hello\", 7); print s }",
+        "   he|007\n",
+    );
+    expect_output(
+        "begin { fmt = \"%s=%d\\n\" } { printf fmt, \"size\", .size }",
+        "size=42\n",
+    );
+    expect_output("{ printf \"%d%%\\n\", 50 }", "50%\n");
+}
+
+#[test]
+fn unary_operators() {
+    fn expect_output(prog: &str, output: &str) {
+        test_one_file_with_program(
+            "unary_operators",
+            Some(Metadata { size: 42 }),
+            prog,
+            ExpectedOutput::String(output),
+        );
+    }
+
+    expect_output("{ print -1, - 2, -.size, 3 - -1 }", "-1 -2 -42 4\n");
+    expect_output("{ x = 5; print -x * 2, -(x + 1) }", "-10 -6\n");
+    expect_output(
+        "{ print !0, !1, !\"\", not .size }",
+        "True False True False\n",
+    );
+    expect_output("!(.size > 100) { print \"small\" }", "small\n");
+    expect_output("not .size > 100 { print \"small\" }", "small\n");
+    expect_output("not .size > 100 and .size > 50 { print \"medium\" }", "");
+    expect_output("!.size { print \"empty\" }", "");
+    expect_output("{ print !!.size }", "True\n");
+}
+
+#[test]
+fn comparison_and_arithmetic_operators() {
+    fn expect_output(prog: &str, output: &str) {
+        test_one_file_with_program(
+            "comparison_and_arithmetic_operators",
+            Some(Metadata { size: 42 }),
+            prog,
+            ExpectedOutput::String(output),
+        );
+    }
+
+    expect_output("{ print .size % 5, -7 % 3, 2 + 10 % 4 * 2 }", "2 -1 6\n");
+    expect_output(".size != 42 { print \"no\" }", "");
+    expect_output(".size != 41 { print \"yes\" }", "yes\n");
+    expect_output(
+        "{ print \"1\" != 1, .ino != .ino, 1 != 1 }",
+        "True False False\n",
+    );
+    expect_output(
+        "{ print \"abc\" < \"abd\", \"10\" < \"9\", \"Z\" < \"a\", \"b\" >= \"b\" }",
+        "True True True True\n",
+    );
+    expect_output("{ print \"10\" < 9, 10 > \"9\" }", "False True\n");
+    expect_output(
+        ".name > \"a\" and .name <= \"d\" { print \"in range\" }",
+        "in range\n",
+    );
+}
+
+#[test]
+fn string_functions() {
+    fn expect_output(prog: &str, output: &str) {
+        test_one_file_with_program(
+            "string_functions",
+            Some(Metadata { size: 42 }),
+            prog,
+            ExpectedOutput::String(output),
+        );
+    }
+
+    expect_output(
+        "{ print \"a\" .. 1 + 2 .. \"b\", .size .. \"B\" }",
+        "a3b 42B\n",
+    );
+    expect_output(
+        ".name .. \"!\" == \"string_functions-file!\" { print \"yes\" }",
+        "yes\n",
+    );
+    expect_output(
+        "{ print length(.name), length(\"\"), length(\"né\") }",
+        "21 0 2\n",
+    );
+    expect_output(
+        "{ print substr(.name, 8), substr(.name, 1, 6), substr(\"hello\", 0, 2), substr(\"hi\", 5) }",
+        "functions-file string h \n",
+    );
+    expect_output(
+        "{ print index(.name, \"_\"), index(.name, \"z\"), index(\"héllo\", \"l\") }",
+        "7 0 3\n",
+    );
+    expect_output(
+        "{ print toupper(.name), tolower(\"ABC\") }",
+        "STRING_FUNCTIONS-FILE abc\n",
+    );
+    expect_output("{ print \"[\" .. trim(\"  a b  \") .. \"]\" }", "[a b]\n");
+    expect_output(
+        "{ print sub(\"[a-z]+\", \"X\", .name), gsub(\"[a-z]+\", \"X\", .name) }",
+        "X_functions-file X_X-X\n",
+    );
+    expect_output(
+        "{ pat = \"(\\w+)-\"; print gsub(pat, \"<$1>\", .name) }",
+        "<string_functions>file\n",
+    );
+    expect_output(
+        "{ n = split(.name, parts, \"_\"); print n, parts[1], parts[2] }",
+        "2 string functions-file\n",
+    );
+    expect_output(
+        "{ arr[5] = 1; n = split(\" a  b c \", arr); print n; for (k in arr) { x += k }; print x }",
+        "3\n6\n",
+    );
+    expect_output(
+        "{ print split(\"\", arr, \",\"), split(\"abc\", arr, \"\"), arr[3] }",
+        "0 3 c\n",
+    );
+    expect_output(
+        "function ext(s) { n = split(s, parts, \".\"); return parts[n] } { print ext(\"a.tar.gz\") }",
+        "gz\n",
+    );
+}
+
+#[test]
+fn path_attributes() {
+    let state = TestState::setup("path_attributes").unwrap();
+
+    let dir = state.test_subdir();
+    std::fs::create_dir_all(dir.join("a/b")).unwrap();
+    state.create_file("a/b/archive.tar.gz", None).unwrap();
+    state.create_file("a/.hidden", None).unwrap();
+
+    let args = Args {
+        path: dir.clone(),
+        prog:
+            ".type == \"file\" { print .relpath, .depth, .ext, .stem, .parent == dirname(.path) }"
+                .to_string(),
+        n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
+    };
+
+    let mut buf = Buffer::new();
+    puffin::driver(&args, &mut buf).unwrap();
+    assert_eq!(
+        buf.sorted_lines(),
+        vec![
+            "a/.hidden 2  .hidden True",
+            "a/b/archive.tar.gz 3 gz archive.tar True",
+        ]
+    );
+
+    let args = Args {
+        path: dir.clone(),
+        prog: ".name == \"archive.tar.gz\" { print realpath(join_path(.parent, \"..\", \".\")) == realpath(dirname(.parent)) }"
+            .to_string(),
+        n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
+    };
+
+    let mut buf = Buffer::new();
+    puffin::driver(&args, &mut buf).unwrap();
+    assert_eq!(buf, "True\n");
+
+    state.cleanup();
+}
+
+#[test]
+fn path_functions() {
+    fn expect_output(prog: &str, output: &str) {
+        test_one_file_with_program("path_functions", None, prog, ExpectedOutput::String(output));
+    }
+
+    expect_output(
+        "{ print dirname(\"a/b/c\"), dirname(\"c\"), dirname(\"/c\"), dirname(\"/\") }",
+        "a/b . / /\n",
+    );
+    expect_output(
+        "{ print basename(\"a/b/c.txt\"), basename(\"a/b/\"), basename(\"/\") }",
+        "c.txt b /\n",
+    );
+    expect_output(
+        "{ print join_path(\"a\", \"b\", \"c\"), join_path(\"a/\", \"b\"), join_path(\"a\", \"/b\") }",
+        "a/b/c a/b /b\n",
+    );
+    expect_output(
+        "{ print .depth, .relpath, relpath_component(1) == \"\" }",
+        "0 . True\n",
+    );
+}
+
+#[test]
+fn size_and_duration_literals() {
+    test_one_file_with_program(
+        "size_and_duration_literals",
+        Some(Metadata { size: 2048 }),
+        ".size > 2K and .size <= 2Ki { print 1M, 1Mi, 1h + 30m }",
+        ExpectedOutput::String("1000000 1048576 5400\n"),
+    );
+}
+
+#[test]
+fn time() {
+    fn expect_output(prog: &str, output: &str) {
+        test_one_file_with_program("time", None, prog, ExpectedOutput::String(output));
+    }
+
+    expect_output(
+        ".mtime <= now and .mtime > now - 1m { print \"recent\" }",
+        "recent\n",
+    );
+    expect_output(
+        ".age >= 0 and .age < 1m and .mage == .age { print \"young\" }",
+        "young\n",
+    );
+    expect_output(".cage > 1d or .aage > 1d { print \"old\" }", "");
+    expect_output("{ print now - now, now == now }", "0 True\n");
+    expect_output(
+        "{ print date(\"2024-01-31\"), date(\"2024-01-31 13:45\") + 1d }",
+        "2024-01-31 00:00:00 2024-02-01 13:45:00\n",
+    );
+    expect_output(
+        "{ print date(\"2024-01-31 10:00\") - date(\"2024-01-31\"), date(\"2024-01-31\") < date(\"2024-02-01\") }",
+        "36000 True\n",
+    );
+    expect_output(
+        "{ print strftime(\"%d/%m/%Y %H:%M\", date(\"2024-01-31 13:45\")) }",
+        "31/01/2024 13:45\n",
+    );
+    expect_output(
+        "{ print strftime(\"%Y\", .mtime) == strftime(\"%Y\", now) }",
+        "True\n",
+    );
+}
+
+#[test]
+fn nanosecond_timestamps() {
+    let state = TestState::setup("nanosecond_timestamps").unwrap();
+
+    let second = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+    for (name, millis) in [("a", 100), ("b", 600), ("c", 0)] {
+        let path = state.create_file(name, None).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(second + std::time::Duration::from_millis(millis))
+            .unwrap();
+    }
+
+    let args = Args {
+        path: state.test_subdir(),
+        prog: ".type == \"file\" { t[.name] = .mtime; ns[.name] = .mtime_ns }
+            .mtime == 1700000000 { same_second += 1 }
+            end {
+                print same_second, t[\"a\"] > 1700000000, t[\"a\"] <= 1700000000;
+                print t[\"a\"] < t[\"b\"], t[\"a\"] == t[\"b\"], t[\"a\"] > t[\"c\"];
+                print ns[\"b\"] - ns[\"a\"], t[\"b\"] - t[\"a\"], ns[\"c\"];
+                print strftime(\"%S.%f\", t[\"b\"])
+            }"
+        .to_string(),
+        n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
+    };
+
+    let mut buf = Buffer::new();
+    puffin::driver(&args, &mut buf).unwrap();
+    assert_eq!(
+        buf,
+        "3 False True\nTrue False True\n500000000 0 1700000000000000000\n20.600000000\n"
+    );
+
+    state.cleanup();
+}
+
+#[test]
+fn statx_fields() {
+    let state = TestState::setup("statx_fields").unwrap();
+    let path = state.create_file("file", None).unwrap();
+
+    let args = Args {
+        path: path.clone(),
+        prog: "{ print .mnt_id > 0, .attributes == \"\", .btime <= now; printf \"%d\\n\", .btime }"
+            .to_string(),
+        n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
+    };
+
+    // Whether the birth time is available depends on the filesystem that the tests run on:
+    let mut buf = Buffer::new();
+    match std::fs::metadata(&path).unwrap().created() {
+        Ok(created) => {
+            puffin::driver(&args, &mut buf).unwrap();
+            let secs = created
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            assert_eq!(buf, &*format!("True True True\n{secs}\n"));
+        }
+        Err(_) => assert!(puffin::driver(&args, &mut buf).is_err()),
+    }
+
+    // The mask says which of the fields are there:
+    let args = Args {
+        path: path.clone(),
+        prog: "{ print .statx_mask ~ \"^type,mode,\", .statx_mask ~ \"btime\" }".to_string(),
+        n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
+    };
+
+    let mut buf = Buffer::new();
+    puffin::driver(&args, &mut buf).unwrap();
+    let has_btime = std::fs::metadata(&path).unwrap().created().is_ok();
+    assert_eq!(
+        buf,
+        &*format!("True {}\n", if has_btime { "True" } else { "False" })
+    );
+
+    state.cleanup();
+}
+
+#[test]
+fn extended_attributes() {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let state = TestState::setup("extended_attributes").unwrap();
+    let path = state.create_file("file", None).unwrap();
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    for (name, value) in [("user.owner", "alice"), ("user.project", "puffin")] {
+        let name = CString::new(name).unwrap();
+        // SAFETY: the path and name are valid C strings, and the value has the length given.
+        let res = unsafe {
+            libc::setxattr(
+                c_path.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+            )
+        };
+        // Not every filesystem that the tests run on supports user xattrs:
+        if res != 0 {
+            state.cleanup();
+            return;
+        }
+    }
+
+    let args = Args {
+        path: path.clone(),
+        prog: "{ print xattr(.path, \"user.owner\"), xattr(.path, \"user.missing\") == \"\";
+            n = split(.xattrs, names, \",\");
+            for (i in names) { if (names[i] == \"user.project\") { print \"found\", n } } }"
+            .to_string(),
+        n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
+    };
+
+    let mut buf = Buffer::new();
+    puffin::driver(&args, &mut buf).unwrap();
+    assert_eq!(buf, "alice True\nfound 2\n");
+
+    state.cleanup();
+}
+
+#[test]
+fn hash_contents() {
+    let state = TestState::setup("hash_contents").unwrap();
+    state.make_tree("tree", 1, 1, 0, None).unwrap();
+    let dir = state.get_path("tree");
+    std::fs::write(dir.join("a"), "abc").unwrap();
+    std::fs::write(dir.join("b"), "abc").unwrap();
+    std::fs::write(dir.join("c"), "abcd").unwrap();
+
+    // Hashing a directory fails, but doesn't stop the walk:
+    let args = Args {
+        path: dir,
+        prog: "{ seen[hash(.path, \"sha256\")] += 1 }
+            .name == \"a\" {
+                print hash(.path, \"md5\"), hash(.path, \"sha1\");
+                print hash(.path, \"blake3\") == hash(.path, \"blake3\"), length(hash(.path, \"xxh3\"))
+            }
+            end { for (h in seen) { if (seen[h] > 1) { print \"duplicates\", seen[h], h } } }"
+            .to_string(),
+        n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
+    };
+
+    let mut buf = Buffer::new();
+    puffin::driver(&args, &mut buf).unwrap();
+
+    assert_eq!(
+        buf.sorted_lines(),
+        vec![
+            "900150983cd24fb0d6963f7d28e17f72 a9993e364706816aba3e25717850c26c9cd0d89d",
+            "True 16",
+            "duplicates 2 ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ]
+    );
+
+    state.cleanup();
+}
+
+#[test]
+fn file_contents() {
+    let state = TestState::setup("file_contents").unwrap();
+    state.make_tree("tree", 1, 1, 0, None).unwrap();
+    let dir = state.get_path("tree");
+    std::fs::write(dir.join("main.rs"), "fn main() {\n    println!(\"hi\");\n}").unwrap();
+    std::fs::write(dir.join("app.conf"), "user=me\npassword=x\n# password\n").unwrap();
+    std::fs::write(dir.join("data.bin"), b"\x7fELF\x00\x01\npassword\n").unwrap();
+    std::fs::write(dir.join("empty"), "").unwrap();
+
+    let prog = ".depth > 0 {
+            print .relpath, .lines, .is_text, .is_binary, contains(.path, \"pass.ord\"),
+                grep_count(.path, \"^[a-z]+=\")
+        }";
+
+    for max_bytes in [None, Some(12)] {
+        let args = Args {
+            path: dir.clone(),
+            prog: prog.to_string(),
+            n_threads: 1,
+            follow: false,
+            max_bytes,
+            effective_ids: false,
+        };
+
+        let mut buf = Buffer::new();
+        puffin::driver(&args, &mut buf).unwrap();
+
+        let expected = match max_bytes {
+            None => vec![
+                "app.conf 3 True False True 2",
+                "data.bin 2 False True True 0",
+                "empty 0 True False False 0",
+                "main.rs 3 True False False 0",
+                "subdir_0 0 False False False 0",
+            ],
+            // Only the start of each file is read, but that doesn't change whether it is text:
+            Some(_) => vec![
+                "app.conf 2 True False False 1",
+                "data.bin 2 False True False 0",
+                "empty 0 True False False 0",
+                "main.rs 1 True False False 0",
+                "subdir_0 0 False False False 0",
+            ],
+        };
+        assert_eq!(buf.sorted_lines(), expected);
+    }
+
+    // Another file's contents are only read through a symlink when following symlinks:
+    std::os::unix::fs::symlink("app.conf", dir.join("link")).unwrap();
+    for follow in [false, true] {
+        let args = Args {
+            path: dir.clone(),
+            prog: ".name == \"main.rs\" {
+                    print contains(.parent .. \"/link\", \"password\"),
+                        grep_count(.parent .. \"/link\", \"=\")
+                }"
+            .to_string(),
+            n_threads: 1,
+            follow,
+            max_bytes: None,
+            effective_ids: false,
+        };
+
+        let mut buf = Buffer::new();
+        puffin::driver(&args, &mut buf).unwrap();
+        assert_eq!(buf, if follow { "True 2\n" } else { "False 0\n" });
+    }
+
+    state.cleanup();
+}
+
+#[test]
+fn magic_numbers() {
+    let state = TestState::setup("magic_numbers").unwrap();
+    state.make_tree("tree", 1, 1, 0, None).unwrap();
+    let dir = state.get_path("tree");
+    std::fs::write(dir.join("tool"), b"\x7fELF\x02\x01\x01\0\0\0\0\0").unwrap();
+    std::fs::write(dir.join("logo.png"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+    std::fs::write(dir.join("build.sh"), "#!/bin/bash\nmake\n").unwrap();
+    std::fs::write(dir.join("README"), "hello\n").unwrap();
+
+    let args = Args {
+        path: dir,
+        prog: ".depth > 0 { print .name, .mime, .magic }
+            .mime == \"application/x-executable\" { print \"stray binary:\", .name }"
+            .to_string(),
+        n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
+    };
+
+    let mut buf = Buffer::new();
+    puffin::driver(&args, &mut buf).unwrap();
+
+    assert_eq!(
+        buf.sorted_lines(),
+        vec![
+            "README text/plain text",
+            "build.sh text/x-shellscript bash script",
+            "logo.png image/png PNG image",
+            "stray binary: tool",
+            "subdir_0  ",
+            "tool application/x-executable ELF",
+        ]
+    );
+
+    state.cleanup();
+}
+
+#[test]
+fn user_and_group_names() {
+    use nix::unistd::{getegid, geteuid, Group, User};
+
+    let user = User::from_uid(geteuid()).unwrap().unwrap().name;
+    let group = Group::from_gid(getegid()).unwrap().unwrap().name;
+    let (uid, gid) = (geteuid().as_raw(), getegid().as_raw());
+    // UIDs and GIDs compare by number:
+    let same_id = uid == gid;
+    let other_uid = uid + 1;
+
+    // Names that don't exist aren't equal to anything, whether they're literals or not, and names
+    // that are all digits are IDs:
+    let prog = format!(
+        "{{ print .owner == \"{user}\", \"{group}\" == .group, .owner_name, .group_name;
+            u = \"{user}\"; g = \"{group}\"; nobody = \"no such user\";
+            print .owner == u, .group != g, .owner == nobody, .owner == .owner_name;
+            print .owner == .group;
+            gi = \"{gid}\";
+            print .owner == \"no such user\", \"no such group\" != .group, .owner == \"{uid}\",
+                .group == gi, .owner == \"{other_uid}\" }}"
+    );
+
+    test_one_file_with_program(
+        "user_and_group_names",
+        None,
+        &prog,
+        ExpectedOutput::String(&format!(
+            "True True {user} {group}\nTrue False False True\n{}\nFalse True True True False\n",
+            if same_id { "True" } else { "False" }
+        )),
+    );
+}
+
+#[test]
+fn permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let state = TestState::setup("permissions").unwrap();
+    state.make_tree("tree", 0, 0, 0, None).unwrap();
+    let dir = state.get_path("tree");
+    for (name, mode) in [("shared", 0o666), ("script", 0o755), ("setuid", 0o4755)] {
+        let path = state.create_file(format!("tree/{name}"), None).unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    let args = Args {
+        path: dir,
+        prog: ".type == \"file\" {
+                print .name, .perms, .mode & 0o7777, .mode & 0o777 == 0o755, (.mode | 0o022) ^ 0o022;
+                print .name, perm(.mode, \"o+w\"), perm(.mode, \"u+s\"), perm(.mode, \"g+x,o-w\"),
+                    perm(.mode, \"/0111\"), perm(.mode, \"-4111\"), perm(.mode, \"755\")
+            }"
+        .to_string(),
+        n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
+    };
+
+    let mut buf = Buffer::new();
+    puffin::driver(&args, &mut buf).unwrap();
+
+    assert_eq!(
+        buf.sorted_lines(),
+        vec![
+            "script -rwxr-xr-x 493 True 33261",
+            "script False False True True False True",
+            "setuid -rwsr-xr-x 2541 True 35309",
+            "setuid False True True True True False",
+            "shared -rw-rw-rw- 438 False 33188",
+            "shared True False False False False False",
+        ]
+    );
+
+    state.cleanup();
+}
+
+#[test]
+fn access_checks() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let state = TestState::setup("access_checks").unwrap();
+    state.make_tree("tree", 1, 1, 0, None).unwrap();
+    let dir = state.get_path("tree");
+    for (name, mode) in [("data", 0o644), ("script", 0o755)] {
+        let path = state.create_file(format!("tree/{name}"), None).unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    // The checks use the real or effective IDs, which are the same when running the tests:
+    for effective_ids in [false, true] {
+        let args = Args {
+            path: dir.clone(),
+            prog: ".depth > 0 { print .name, .readable, .writable, .executable }
+                .type == \"file\" and .writable and not .executable { print \"writable data:\", .name }"
+                .to_string(),
+            n_threads: 1,
+            follow: false,
+            max_bytes: None,
+            effective_ids,
+        };
+
+        let mut buf = Buffer::new();
+        puffin::driver(&args, &mut buf).unwrap();
+
+        assert_eq!(
+            buf.sorted_lines(),
+            vec![
+                "data True True False",
+                "script True True True",
+                "subdir_0 True True True",
+                "writable data: data",
+            ]
+        );
+    }
+
+    state.cleanup();
+}
+
+#[test]
+fn device_numbers_and_filesystems() {
+    use std::os::unix::fs::MetadataExt;
+
+    let state = TestState::setup("device_numbers_and_filesystems").unwrap();
+    let path = state.create_file("file", None).unwrap();
+    let dev = std::fs::metadata(&path).unwrap().dev();
+
+    // Filesystem types are cached by device, so the links have to be looked at first for their
+    // own lookups to be tested. Neither a link to a file nor a broken link can have "link/.."
+    // looked up.
+    state.make_tree("links", 0, 0, 0, None).unwrap();
+    std::os::unix::fs::symlink("../file", state.get_path("links/file_link")).unwrap();
+    std::os::unix::fs::symlink("missing", state.get_path("links/broken_link")).unwrap();
+    for follow in [false, true] {
+        let args = Args {
+            path: state.get_path("links"),
+            prog: ".depth > 0 { print .mountpoint, .fstype }".to_string(),
+            n_threads: 1,
+            follow,
+            max_bytes: None,
+            effective_ids: false,
+        };
+
+        let mut buf = Buffer::new();
+        puffin::driver(&args, &mut buf).unwrap();
+        let lines = buf.sorted_lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].len() > "False ".len(), "{lines:?}");
+        assert!(lines.iter().all(|l| *l == lines[0]), "{lines:?}");
+    }
+
+    // SAFETY: major() and minor() are only arithmetic on their arguments.
+    let (major, minor) = unsafe { (libc::major(dev), libc::minor(dev)) };
+
+    let args = Args {
+        path: path.clone(),
+        prog: format!(
+            "{{ print .dev, major(.dev), minor(.dev), major({dev}) == {major};
+                printf \"%d\\n\", .dev;
+                print .fstype != \"\", .mountpoint }}"
+        ),
+        n_threads: 1,
+        follow: false,
+        max_bytes: None,
+        effective_ids: false,
+    };
+
+    let mut buf = Buffer::new();
+    puffin::driver(&args, &mut buf).unwrap();
+    assert_eq!(
+        buf,
+        &*format!("{major}:{minor} {major} {minor} True\n{dev}\nTrue False\n")
+    );
+
+    state.cleanup();
+}